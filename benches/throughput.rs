@@ -0,0 +1,70 @@
+use async_io_map::{AsyncMapReader, BoxedAsyncMapReader};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use futures_lite::{future::block_on, AsyncReadExt};
+
+const LEN: usize = 1 << 20;
+
+fn identity(c: &mut Criterion) {
+    let data = vec![b'a'; LEN];
+    let mut group = c.benchmark_group("identity");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    // The same transform, called through the boxed default and directly
+    group.bench_function("1MB/boxed", |b| {
+        b.iter_batched_ref(
+            || Vec::with_capacity(LEN),
+            |out| {
+                let mut reader: BoxedAsyncMapReader<_> =
+                    AsyncMapReader::new(&data[..], |_: &mut [u8]| {});
+                block_on(reader.read_to_end(out)).unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("1MB/generic", |b| {
+        b.iter_batched_ref(
+            || Vec::with_capacity(LEN),
+            |out| {
+                let mut reader = AsyncMapReader::from_fn(&data[..], |_: &mut [u8]| {});
+                block_on(reader.read_to_end(out)).unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn per_byte(c: &mut Criterion) {
+    let data = vec![b'a'; LEN];
+    let mut group = c.benchmark_group("per_byte");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    group.bench_function("1MB", |b| {
+        b.iter_batched_ref(
+            || Vec::with_capacity(LEN),
+            |out| {
+                let mut reader = AsyncMapReader::map_bytes(&data[..], |b| b ^ 0x20);
+                block_on(reader.read_to_end(out)).unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn byte_at_a_time(c: &mut Criterion) {
+    const SMALL: usize = 64 * 1024;
+    let data = vec![b'a'; SMALL];
+    let mut group = c.benchmark_group("byte_at_a_time");
+    group.throughput(Throughput::Bytes(SMALL as u64));
+    group.bench_function("64KB", |b| {
+        b.iter(|| {
+            let mut reader =
+                AsyncMapReader::new(&data[..], |buf: &mut [u8]| buf.make_ascii_uppercase());
+            let mut byte = [0u8; 1];
+            block_on(async { while reader.read(&mut byte).await.unwrap() == 1 {} })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, identity, per_byte, byte_at_a_time);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+use crate::MapReadFn;
+
+/// A read mapping function keeping some state across chunks, e.g. the model of an adaptive codec.
+///
+/// The state is stored inline and passed to the mapping function along with every chunk.
+/// To recover it once reading is done, create the reader with [`AsyncMapReader::from_fn`](crate::AsyncMapReader::from_fn),
+/// then call [`AsyncMapReader::into_process_fn`](crate::AsyncMapReader::into_process_fn) and [`AdaptiveMap::into_state`].
+pub struct AdaptiveMap<S, F> {
+    state: S,
+    map_fn: F,
+}
+
+impl<S, F: FnMut(&mut S, &mut [u8])> AdaptiveMap<S, F> {
+    /// Creates a new `AdaptiveMap` mapping chunks with `map_fn`, starting from `state`.
+    pub fn new(state: S, map_fn: F) -> Self {
+        Self { state, map_fn }
+    }
+
+    /// Returns a reference to the current state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns a mutable reference to the current state.
+    pub fn state_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+
+    /// Consumes the `AdaptiveMap` and returns the state.
+    pub fn into_state(self) -> S {
+        self.state
+    }
+}
+
+impl<S, F: FnMut(&mut S, &mut [u8])> MapReadFn for AdaptiveMap<S, F> {
+    fn map_read(&mut self, buf: &mut [u8]) {
+        (self.map_fn)(&mut self.state, buf)
+    }
+}
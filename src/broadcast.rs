@@ -0,0 +1,187 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncWrite};
+
+use crate::{AsyncMapWriter, MapWriteFn};
+
+/// How a [`Broadcast`] handles a write error on one of its writers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BroadcastPolicy {
+    /// Return the first error encountered, aborting the broadcast.
+    #[default]
+    FailFast,
+    /// Stop writing to a writer once it fails, but keep writing to the others.
+    ///
+    /// An error is only returned once every writer has failed.
+    /// The errors of the failed writers can be inspected with [`Broadcast::errors`].
+    BestEffort,
+}
+
+struct Target<W> {
+    writer: W,
+    offset: usize, // How much of the current slice this writer has accepted
+    error: Option<io::Error>,
+    closed: bool,
+}
+
+/// An `AsyncWrite` that writes everything to several underlying writers.
+///
+/// A write only completes once every writer has accepted the whole slice, so callers must keep passing
+/// the same slice to `poll_write` until it returns `Poll::Ready`, as [`AsyncMapWriter`] does.
+/// This is usually constructed through [`AsyncMapWriter::broadcast`].
+pub struct Broadcast<W> {
+    targets: Vec<Target<W>>,
+    policy: BroadcastPolicy,
+}
+
+impl<W: AsyncWrite + Unpin> Broadcast<W> {
+    /// Creates a new `Broadcast` writing to all of `writers`.
+    pub fn new(writers: Vec<W>, policy: BroadcastPolicy) -> Self {
+        let targets = writers
+            .into_iter()
+            .map(|writer| Target {
+                writer,
+                offset: 0,
+                error: None,
+                closed: false,
+            })
+            .collect();
+        Self { targets, policy }
+    }
+
+    /// Returns the errors of the writers that failed, along with their index.
+    ///
+    /// This is only populated with [`BroadcastPolicy::BestEffort`].
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &io::Error)> {
+        self.targets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, t)| t.error.as_ref().map(|e| (i, e)))
+    }
+
+    /// Consumes the `Broadcast` and returns the underlying writers.
+    pub fn into_inner(self) -> Vec<W> {
+        self.targets.into_iter().map(|t| t.writer).collect()
+    }
+
+    /// Records an error according to the policy, returning it if the broadcast should abort.
+    fn fail(&mut self, index: usize, error: io::Error) -> Option<io::Error> {
+        match self.policy {
+            BroadcastPolicy::FailFast => Some(error),
+            BroadcastPolicy::BestEffort => {
+                self.targets[index].error = Some(error);
+                None
+            }
+        }
+    }
+
+    /// Returns an error if every writer has failed.
+    fn all_failed(&self) -> Option<io::Error> {
+        if !self.targets.is_empty() && self.targets.iter().all(|t| t.error.is_some()) {
+            Some(io::Error::other("all broadcast writers failed"))
+        } else {
+            None
+        }
+    }
+
+    /// Polls `op` on every writer that hasn't failed yet, until all of them are ready.
+    fn poll_all(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut op: impl FnMut(&mut Target<W>, &mut Context<'_>) -> Poll<io::Result<()>>,
+    ) -> Poll<io::Result<()>> {
+        let mut pending = false;
+        for i in 0..self.targets.len() {
+            if self.targets[i].error.is_some() {
+                continue;
+            }
+            match op(&mut self.targets[i], cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => {
+                    if let Some(e) = self.fail(i, e) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                Poll::Pending => pending = true,
+            }
+        }
+        if pending {
+            return Poll::Pending;
+        }
+        match self.all_failed() {
+            Some(e) => Poll::Ready(Err(e)),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for Broadcast<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let res = this.poll_all(cx, |target, cx| {
+            while target.offset < buf.len() {
+                let n = match Pin::new(&mut target.writer).poll_write(cx, &buf[target.offset..]) {
+                    Poll::Ready(Ok(0)) => {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            "write zero",
+                        )))
+                    }
+                    Poll::Ready(Ok(n)) => n,
+                    Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => 0,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+                target.offset += n;
+            }
+            Poll::Ready(Ok(()))
+        });
+        if res.is_ready() {
+            // Either everyone accepted the slice or the broadcast failed, start over on the next one
+            this.targets.iter_mut().for_each(|t| t.offset = 0);
+        }
+        res.map_ok(|()| buf.len())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut()
+            .poll_all(cx, |target, cx| Pin::new(&mut target.writer).poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_all(cx, |target, cx| {
+            if !target.closed {
+                ready!(Pin::new(&mut target.writer).poll_close(cx))?;
+                target.closed = true;
+            }
+            Poll::Ready(Ok(()))
+        })
+    }
+}
+
+impl<'a, W: AsyncWrite + Unpin> AsyncMapWriter<'a, Broadcast<W>> {
+    /// Creates a new `AsyncMapWriter` writing the mapped data to every writer in `writers`.
+    ///
+    /// The mapping function is applied once per chunk, and the result is written to all writers.
+    /// The first write error is returned, see [`AsyncMapWriter::broadcast_with_policy`] to change this.
+    pub fn broadcast(writers: Vec<W>, process_fn: impl MapWriteFn + 'a) -> Self {
+        Self::broadcast_with_policy(writers, BroadcastPolicy::FailFast, process_fn)
+    }
+
+    /// Creates a new `AsyncMapWriter` writing the mapped data to every writer in `writers`,
+    /// handling write errors according to `policy`.
+    pub fn broadcast_with_policy(
+        writers: Vec<W>,
+        policy: BroadcastPolicy,
+        process_fn: impl MapWriteFn + 'a,
+    ) -> Self {
+        Self::new(Broadcast::new(writers, policy), process_fn)
+    }
+}
@@ -0,0 +1,92 @@
+use std::ops::{Deref, DerefMut};
+
+/// A fixed size byte buffer whose start is aligned to a given boundary.
+///
+/// The alignment is achieved by over-allocating and offsetting into the allocation,
+/// so no custom allocation (and no unsafe code) is needed.
+pub(crate) struct Buffer {
+    storage: Box<[u8]>,
+    offset: usize, // Offset of the aligned start within `storage`
+    len: usize,
+    align: usize,
+    #[cfg(feature = "zeroize")]
+    pub(crate) zeroize: bool, // Whether the storage is wiped when the buffer is dropped or resized
+}
+
+impl Buffer {
+    /// Creates a zeroed buffer of `len` bytes starting at a multiple of `align`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub(crate) fn aligned(len: usize, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let storage = vec![0; len + align - 1].into_boxed_slice();
+        let offset = storage.as_ptr().align_offset(align);
+        assert!(offset < align, "failed to align the buffer");
+        Self {
+            storage,
+            offset,
+            len,
+            align,
+            #[cfg(feature = "zeroize")]
+            zeroize: false,
+        }
+    }
+
+    /// Resizes the buffer to `len` bytes, keeping its contents and alignment.
+    pub(crate) fn resize(&mut self, len: usize) {
+        let mut resized = Self::aligned(len, self.align);
+        #[cfg(feature = "zeroize")]
+        {
+            resized.zeroize = self.zeroize;
+        }
+        let kept = std::cmp::min(len, self.len);
+        resized[..kept].copy_from_slice(&self[..kept]);
+        *self = resized;
+    }
+}
+
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        // A plain copy of the storage could lose the alignment
+        let mut buffer = Self::aligned(self.len, self.align);
+        #[cfg(feature = "zeroize")]
+        {
+            buffer.zeroize = self.zeroize;
+        }
+        buffer.copy_from_slice(self);
+        buffer
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Buffer {
+    /// Overwrites the whole storage with zeros, in a way the compiler can't optimize out.
+    pub(crate) fn wipe(&mut self) {
+        zeroize::Zeroize::zeroize(&mut *self.storage);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.zeroize {
+            self.wipe();
+        }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.storage[self.offset..self.offset + self.len]
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.storage[self.offset..self.offset + self.len]
+    }
+}
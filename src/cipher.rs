@@ -0,0 +1,91 @@
+use futures_lite::{AsyncRead, AsyncWrite};
+
+use crate::{AsyncMapReader, AsyncMapWriter, MapReadFn, MapWriteFn};
+
+/// A length-preserving streaming cipher.
+///
+/// Both methods are called on consecutive pieces of the stream, in order, along with the position
+/// of the piece in the stream, so position-dependent ciphers (e.g. counter mode) can derive their keystream from it.
+pub trait Cipher {
+    /// Encrypts `buf` in place, `offset` being the position of its first byte in the stream.
+    fn encrypt(&mut self, buf: &mut [u8], offset: u64);
+
+    /// Decrypts `buf` in place, `offset` being the position of its first byte in the stream.
+    fn decrypt(&mut self, buf: &mut [u8], offset: u64);
+}
+
+/// A write mapping function encrypting the data with a [`Cipher`].
+pub struct EncryptWrite<C> {
+    cipher: C,
+    offset: u64, // Position of the next byte in the stream
+}
+
+impl<C: Cipher> EncryptWrite<C> {
+    /// Creates a new `EncryptWrite` encrypting with `cipher`.
+    pub fn new(cipher: C) -> Self {
+        Self::with_offset(cipher, 0)
+    }
+
+    /// Creates a new `EncryptWrite` encrypting with `cipher`, starting at `offset` in the stream,
+    /// e.g. to append to data encrypted earlier.
+    pub fn with_offset(cipher: C, offset: u64) -> Self {
+        Self { cipher, offset }
+    }
+
+    /// Returns the position of the next byte to encrypt.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<C: Cipher> MapWriteFn for EncryptWrite<C> {
+    fn map_write(&mut self, buf: &mut Vec<u8>) {
+        self.cipher.encrypt(buf, self.offset);
+        self.offset += buf.len() as u64;
+    }
+}
+
+/// A read mapping function decrypting the data with a [`Cipher`].
+pub struct DecryptRead<C> {
+    cipher: C,
+    offset: u64, // Position of the next byte in the stream
+}
+
+impl<C: Cipher> DecryptRead<C> {
+    /// Creates a new `DecryptRead` decrypting with `cipher`.
+    pub fn new(cipher: C) -> Self {
+        Self::with_offset(cipher, 0)
+    }
+
+    /// Creates a new `DecryptRead` decrypting with `cipher`, starting at `offset` in the stream,
+    /// e.g. to read from the middle of the encrypted data.
+    pub fn with_offset(cipher: C, offset: u64) -> Self {
+        Self { cipher, offset }
+    }
+
+    /// Returns the position of the next byte to decrypt.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<C: Cipher> MapReadFn for DecryptRead<C> {
+    fn map_read(&mut self, buf: &mut [u8]) {
+        self.cipher.decrypt(buf, self.offset);
+        self.offset += buf.len() as u64;
+    }
+}
+
+impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
+    /// Creates a new `AsyncMapWriter` encrypting the data written with `cipher`.
+    pub fn encrypt(writer: W, cipher: impl Cipher + 'a) -> Self {
+        Self::new(writer, EncryptWrite::new(cipher))
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncMapReader<'a, R> {
+    /// Create a new wrapper decrypting the data read with `cipher`.
+    pub fn decrypt(reader: R, cipher: impl Cipher + 'a) -> Self {
+        Self::new(reader, DecryptRead::new(cipher))
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    io::IoSlice,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncBufRead, AsyncRead, AsyncWrite};
+
+use crate::{AsyncMapReader, AsyncMapWriter, MapReadFn, MapWriteFn, TryMapReadFn, TryMapWriteFn};
+
+pin_project_lite::pin_project! {
+  /// Wraps a tokio reader or writer so it can be used as the underlying reader or writer
+  /// of an [`AsyncMapReader`] or [`AsyncMapWriter`].
+  ///
+  /// This is usually constructed through [`AsyncMapReader::from_tokio`] or [`AsyncMapWriter::from_tokio`].
+  /// The resulting wrappers implement tokio's `AsyncRead` and `AsyncWrite` themselves.
+  pub struct TokioCompat<T> {
+      #[pin]
+      inner: T,
+  }
+}
+
+impl<T> TokioCompat<T> {
+    /// Creates a new `TokioCompat` around `inner`.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Consumes the `TokioCompat` and returns the wrapped reader or writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: tokio::io::AsyncRead> AsyncRead for TokioCompat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut read_buf = tokio::io::ReadBuf::new(buf);
+        ready!(self.project().inner.poll_read(cx, &mut read_buf))?;
+        Poll::Ready(Ok(read_buf.filled().len()))
+    }
+}
+
+impl<T: tokio::io::AsyncWrite> AsyncWrite for TokioCompat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+impl<'a, R: tokio::io::AsyncRead> AsyncMapReader<'a, TokioCompat<R>> {
+    /// Create a new wrapper around a tokio reader with a processing function
+    pub fn from_tokio(reader: R, process_fn: impl MapReadFn + 'a) -> Self {
+        Self::new(TokioCompat::new(reader), process_fn)
+    }
+}
+
+impl<'a, W: tokio::io::AsyncWrite> AsyncMapWriter<'a, TokioCompat<W>> {
+    /// Creates a new `AsyncMapWriter` around a tokio writer.
+    pub fn from_tokio(writer: W, process_fn: impl MapWriteFn + 'a) -> Self {
+        Self::new(TokioCompat::new(writer), process_fn)
+    }
+}
+
+impl<R: AsyncRead, F: TryMapReadFn> tokio::io::AsyncRead for AsyncMapReader<'_, R, F> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let rem = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let amt = std::cmp::min(rem.len(), buf.remaining());
+        buf.put_slice(&rem[..amt]);
+        self.consume(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite, F: TryMapWriteFn> tokio::io::AsyncWrite for AsyncMapWriter<'_, W, F> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    /// Maps the final chunk and closes the underlying writer, like [`AsyncWrite::poll_close`].
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncRead};
+
+use crate::{AsyncMapReader, DEFAULT_BUFFER_SIZE};
+
+type SplitFn<'a> = Box<dyn FnMut(&[u8]) -> (Vec<u8>, Vec<u8>) + 'a>;
+type SidebandFn<'a> = Box<dyn FnMut(Vec<u8>) + 'a>;
+
+pin_project_lite::pin_project! {
+  /// An `AsyncRead` splitting the data of the underlying reader into a main stream and a sideband.
+  ///
+  /// Every chunk read is passed to a split function, which returns the bytes of the main stream
+  /// and the bytes of the sideband. The main stream is served through `AsyncRead`,
+  /// while the sideband bytes are handed to a sink as soon as the chunk is split.
+  /// This is usually constructed through [`AsyncMapReader::with_demux`].
+  pub struct Demux<'a, R> {
+      #[pin]
+      inner: R,
+      split_fn: SplitFn<'a>,
+      sideband: SidebandFn<'a>,
+      raw: Vec<u8>, // Buffer for reading from the underlying reader
+      out: Vec<u8>, // Main stream bytes of the last chunk
+      pos: usize, // Position of the next byte to serve in `out`
+      eof: bool, // Whether the underlying reader has reported EOF
+  }
+}
+
+impl<'a, R: AsyncRead> Demux<'a, R> {
+    /// Creates a new `Demux` splitting the data of `reader` with `split_fn`,
+    /// and handing the sideband bytes to `sideband`.
+    ///
+    /// The sink is only called with non-empty sideband data.
+    pub fn new(
+        reader: R,
+        split_fn: impl FnMut(&[u8]) -> (Vec<u8>, Vec<u8>) + 'a,
+        sideband: impl FnMut(Vec<u8>) + 'a,
+    ) -> Self {
+        Self {
+            inner: reader,
+            split_fn: Box::new(split_fn),
+            sideband: Box::new(sideband),
+            raw: vec![0; DEFAULT_BUFFER_SIZE],
+            out: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Consume the wrapper and return the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for Demux<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        loop {
+            if *this.pos < this.out.len() {
+                let amt = std::cmp::min(this.out.len() - *this.pos, buf.len());
+                buf[..amt].copy_from_slice(&this.out[*this.pos..*this.pos + amt]);
+                *this.pos += amt;
+                return Poll::Ready(Ok(amt));
+            }
+            if *this.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            let read_amount = ready!(this.inner.as_mut().poll_read(cx, this.raw))?;
+            if read_amount == 0 {
+                *this.eof = true;
+                continue;
+            }
+            let (main, sideband) = (this.split_fn)(&this.raw[..read_amount]);
+            if !sideband.is_empty() {
+                (this.sideband)(sideband);
+            }
+            *this.out = main;
+            *this.pos = 0;
+        }
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncMapReader<'a, R> {
+    /// Create a [`Demux`] serving the main stream of `reader`,
+    /// handing the sideband bytes split off by `split_fn` to `sideband`.
+    ///
+    /// The `Demux` buffers the main stream itself, so it's returned as is rather than wrapped again.
+    pub fn with_demux(
+        reader: R,
+        split_fn: impl FnMut(&[u8]) -> (Vec<u8>, Vec<u8>) + 'a,
+        sideband: impl FnMut(Vec<u8>) + 'a,
+    ) -> Demux<'a, R> {
+        Demux::new(reader, split_fn, sideband)
+    }
+}
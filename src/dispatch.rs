@@ -0,0 +1,68 @@
+use futures_lite::{io, AsyncRead};
+
+use crate::{AsyncMapReader, ChunkPolicy, MapReadFn, TryMapReadFn};
+
+/// The index of a mapping function in the registry of [`AsyncMapReader::with_dispatch`].
+pub type TransformId = usize;
+
+/// Maps the body of each chunk with the mapping function selected by its header, and strips the header.
+struct Dispatch<'a, S> {
+    header_len: usize,
+    selector: S,
+    transforms: Vec<Box<dyn MapReadFn + 'a>>,
+}
+
+impl<S: Fn(&[u8]) -> TransformId> TryMapReadFn for Dispatch<'_, S> {
+    fn try_map_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.len() < self.header_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chunk is shorter than its header",
+            ));
+        }
+        let (header, body) = buf.split_at_mut(self.header_len);
+        let id = (self.selector)(header);
+        let transform = self.transforms.get_mut(id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chunk header selects unknown transform {id}"),
+            )
+        })?;
+        transform.map_read(body);
+        let body_len = body.len();
+        buf.copy_within(self.header_len.., 0);
+        Ok(body_len)
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncMapReader<'a, R> {
+    /// Create a new wrapper for self-describing chunks of `chunk_len` bytes, each starting with a `header_len` bytes header
+    /// that selects how the rest of the chunk is mapped.
+    ///
+    /// Chunks are split at fixed offsets, see [`ChunkPolicy::Fixed`], so they don't depend on how the
+    /// underlying reader splits its reads. Only the last chunk may be shorter.
+    /// `selector` is called with the header of each chunk, and returns the index in `transforms`
+    /// of the mapping function to apply to the body. Only the mapped bodies are served.
+    ///
+    /// Reads fail with an [`io::ErrorKind::InvalidData`] error if the last chunk is shorter than the header,
+    /// or if a header selects a transform that doesn't exist.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `header_len` is larger than `chunk_len`, or if `chunk_len` is zero.
+    pub fn with_dispatch(
+        reader: R,
+        chunk_len: usize,
+        header_len: usize,
+        selector: impl Fn(&[u8]) -> TransformId + 'a,
+        transforms: Vec<Box<dyn MapReadFn + 'a>>,
+    ) -> Self {
+        assert!(header_len <= chunk_len, "header must fit in a chunk");
+        let dispatch = Dispatch {
+            header_len,
+            selector,
+            transforms,
+        };
+        Self::try_with_chunk_policy(reader, dispatch, ChunkPolicy::Fixed(chunk_len))
+    }
+}
@@ -0,0 +1,74 @@
+use futures_lite::{AsyncRead, AsyncWrite};
+
+use crate::{AsyncMapWriter, AsyncVecMapReader, MapReadFnVec, MapWriteFn};
+
+/// A trait for mapping data by pushing the output piece by piece, rather than rewriting a buffer.
+///
+/// This suits mappings producing a variable amount of output for each input byte, like tokenizers,
+/// see [`AsyncVecMapReader::with_emit`] and [`AsyncMapWriter::with_emit`].
+pub trait MapEmitFn {
+    /// Maps a chunk of `input`, passing the output to `emit` as it's produced.
+    ///
+    /// The emitted bytes are buffered, and served or written in the order they were emitted.
+    fn map_emit(&mut self, input: &[u8], emit: &mut dyn FnMut(&[u8]));
+}
+
+impl<F> MapEmitFn for F
+where
+    F: FnMut(&[u8], &mut dyn FnMut(&[u8])),
+{
+    fn map_emit(&mut self, input: &[u8], emit: &mut dyn FnMut(&[u8])) {
+        self(input, emit)
+    }
+}
+
+/// Adapts a [`MapEmitFn`] to the buffers of the reader and writer.
+struct Emitting<F> {
+    process_fn: F,
+    out: Vec<u8>, // Emitted bytes, copied into the buffer once the chunk is mapped
+}
+
+impl<F: MapEmitFn> Emitting<F> {
+    fn new(process_fn: F) -> Self {
+        Self {
+            process_fn,
+            out: Vec::new(),
+        }
+    }
+
+    fn map(&mut self, buf: &mut Vec<u8>) {
+        let out = &mut self.out;
+        self.process_fn
+            .map_emit(buf, &mut |bytes| out.extend_from_slice(bytes));
+        // Copy rather than swap, so the buffer keeps its own allocation, even if nothing was emitted
+        buf.clear();
+        buf.extend_from_slice(out);
+        out.clear();
+    }
+}
+
+impl<F: MapEmitFn> MapReadFnVec for Emitting<F> {
+    fn map_read(&mut self, buf: &mut Vec<u8>) {
+        self.map(buf)
+    }
+}
+
+impl<F: MapEmitFn> MapWriteFn for Emitting<F> {
+    fn map_write(&mut self, buf: &mut Vec<u8>) {
+        self.map(buf)
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncVecMapReader<'a, R> {
+    /// Create a new wrapper mapping the data read with a function that emits its output incrementally.
+    pub fn with_emit(reader: R, process_fn: impl MapEmitFn + 'a) -> Self {
+        Self::new(reader, Emitting::new(process_fn))
+    }
+}
+
+impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
+    /// Creates a new `AsyncMapWriter` mapping the data written with a function that emits its output incrementally.
+    pub fn with_emit(writer: W, process_fn: impl MapEmitFn + 'a) -> Self {
+        Self::new(writer, Emitting::new(process_fn))
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, AsyncRead};
+
+use crate::{MapWriteFn, DEFAULT_BUFFER_SIZE};
+
+/// A write mapping function transcoding UTF-8 to UTF-16LE.
+///
+/// A character split across chunks is held back until the rest of it is written.
+/// Invalid UTF-8, including a character left incomplete when the writer is closed,
+/// is replaced with U+FFFD REPLACEMENT CHARACTER.
+#[derive(Debug, Default)]
+pub struct Utf8ToUtf16Write {
+    carry: Vec<u8>, // Start of a character split across chunks
+}
+
+impl Utf8ToUtf16Write {
+    /// Creates a new `Utf8ToUtf16Write`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces `buf` with its UTF-16LE encoding, keeping a trailing incomplete character unless `last`.
+    fn transcode(&mut self, buf: &mut Vec<u8>, last: bool) {
+        self.carry.append(buf);
+        let mut input = &self.carry[..];
+        let mut push = |s: &str| {
+            s.encode_utf16()
+                .for_each(|unit| buf.extend_from_slice(&unit.to_le_bytes()))
+        };
+        loop {
+            match std::str::from_utf8(input) {
+                Ok(s) => {
+                    push(s);
+                    input = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = input.split_at(e.valid_up_to());
+                    // `valid_up_to` guarantees this prefix is valid UTF-8
+                    push(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(len) => {
+                            push("\u{FFFD}");
+                            input = &rest[len..];
+                        }
+                        None if last => {
+                            push("\u{FFFD}");
+                            input = &[];
+                            break;
+                        }
+                        None => {
+                            input = rest;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        let kept = input.len();
+        let start = self.carry.len() - kept;
+        self.carry.drain(..start);
+    }
+}
+
+impl MapWriteFn for Utf8ToUtf16Write {
+    fn map_write(&mut self, buf: &mut Vec<u8>) {
+        self.transcode(buf, false);
+    }
+
+    fn map_write_final(&mut self, buf: &mut Vec<u8>) {
+        self.transcode(buf, true);
+    }
+}
+
+pin_project_lite::pin_project! {
+  /// A reader transcoding UTF-16LE data from the underlying reader to UTF-8.
+  ///
+  /// Code units and surrogate pairs split across reads are held back until they are complete.
+  /// Invalid UTF-16, including an odd number of bytes or a lone surrogate at EOF,
+  /// is reported as an [`io::ErrorKind::InvalidData`] error.
+  pub struct Utf16ToUtf8Read<R> {
+      #[pin]
+      inner: R,
+      raw: Vec<u8>, // UTF-16 data read but not yet decoded
+      out: Vec<u8>, // Decoded UTF-8 data
+      pos: usize, // Position of the next byte to serve in `out`
+      eof: bool, // Whether the underlying reader has reported EOF
+  }
+}
+
+impl<R: AsyncRead> Utf16ToUtf8Read<R> {
+    /// Creates a new `Utf16ToUtf8Read` decoding the data of `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            inner: reader,
+            raw: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Consume the wrapper and return the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Decodes the UTF-16LE data in `raw` to `out`, keeping an incomplete trailing character unless `last`.
+fn decode_utf16(raw: &mut Vec<u8>, out: &mut Vec<u8>, last: bool) -> io::Result<()> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-16");
+    if last && !raw.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    let mut units = raw
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect::<Vec<u16>>();
+    // A high surrogate needs the next unit to be decoded
+    if !last
+        && units
+            .last()
+            .is_some_and(|unit| (0xD800..0xDC00).contains(unit))
+    {
+        units.pop();
+    }
+    for c in char::decode_utf16(units.iter().copied()) {
+        let c = c.map_err(|_| invalid())?;
+        out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    raw.drain(..units.len() * 2);
+    Ok(())
+}
+
+impl<R: AsyncRead> AsyncRead for Utf16ToUtf8Read<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        loop {
+            if *this.pos < this.out.len() {
+                let amt = std::cmp::min(this.out.len() - *this.pos, buf.len());
+                buf[..amt].copy_from_slice(&this.out[*this.pos..*this.pos + amt]);
+                *this.pos += amt;
+                return Poll::Ready(Ok(amt));
+            }
+            if *this.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            this.out.clear();
+            *this.pos = 0;
+            let start = this.raw.len();
+            this.raw.resize(start + DEFAULT_BUFFER_SIZE, 0);
+            let read = this.inner.as_mut().poll_read(cx, &mut this.raw[start..]);
+            let read_amount = match read {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(e)) => {
+                    this.raw.truncate(start);
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => {
+                    this.raw.truncate(start);
+                    return Poll::Pending;
+                }
+            };
+            this.raw.truncate(start + read_amount);
+            if read_amount == 0 {
+                *this.eof = true;
+            }
+            decode_utf16(this.raw, this.out, *this.eof)?;
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use futures_lite::{io, AsyncWriteExt};
+
+use crate::{AsyncMapReader, MapReadFn};
+
+/// Maps the contents of the file at `path` through the mapping function, replacing the file.
+///
+/// The mapped data is written to a temporary file next to the original, which is then renamed over it,
+/// so the file is never left half-transformed. If anything fails, the original file is left untouched.
+/// The permissions of the original file are kept.
+pub async fn transform_file(path: impl AsRef<Path>, process_fn: impl MapReadFn) -> io::Result<()> {
+    let path = path.as_ref();
+    let permissions = async_fs::metadata(path).await?.permissions();
+    let (mut tmp, tmp_path) = create_temp_file(path).await?;
+
+    let result = async {
+        let source = async_fs::File::open(path).await?;
+        let mut reader = AsyncMapReader::new(source, process_fn);
+        io::copy(&mut reader, &mut tmp).await?;
+        tmp.flush().await?;
+        tmp.set_permissions(permissions).await?;
+        tmp.sync_all().await?;
+        async_fs::rename(&tmp_path, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = async_fs::remove_file(&tmp_path).await;
+    }
+    result
+}
+
+/// Creates a new temporary file next to `path`, with a name no other call uses.
+///
+/// The name is made unique with the process id and a counter, and the file is only created
+/// if it doesn't exist yet, so an existing file is never clobbered.
+async fn create_temp_file(path: &Path) -> io::Result<(async_fs::File, PathBuf)> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(path.file_name().unwrap_or_default());
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = path.with_file_name(tmp_name);
+        match async_fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .await
+        {
+            Ok(file) => return Ok((file, tmp_path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncBufRead, AsyncRead};
+
+use crate::DEFAULT_BUFFER_SIZE;
+
+/// A trait for mapping data read from an underlying reader, possibly changing its length.
+///
+/// This is the read side counterpart of [`MapWriteFn`](crate::MapWriteFn), for mappings like
+/// decoding or decompression that can't work in place on a fixed size chunk.
+pub trait MapReadFnVec {
+    /// Applies a mapping function to a chunk read from the underlying reader.
+    ///
+    /// `buf` holds at most as many bytes as the capacity of the [`AsyncVecMapReader`],
+    /// and may be grown or shrunk freely; whatever it holds afterwards is served.
+    fn map_read(&mut self, buf: &mut Vec<u8>);
+}
+
+impl<F> MapReadFnVec for F
+where
+    F: FnMut(&mut Vec<u8>),
+{
+    fn map_read(&mut self, buf: &mut Vec<u8>) {
+        self(buf)
+    }
+}
+
+pin_project_lite::pin_project! {
+  /// A wrapper around an `AsyncRead` applying a [`MapReadFnVec`] to the data read.
+  ///
+  /// This behaves like [`AsyncMapReader`](crate::AsyncMapReader), except the buffer is a `Vec<u8>`
+  /// the mapping function can resize. The chunk passed to the mapping function is at most `capacity` bytes,
+  /// but the mapped data may be any length, and is served across as many reads as needed before
+  /// the next chunk is read.
+  pub struct AsyncVecMapReader<'a, R> {
+      #[pin]
+      inner: R,
+      process_fn: Box<dyn MapReadFnVec + 'a>,
+      buf: Vec<u8>, // Mapped data being served
+      pos: usize, // Current position in the buffer
+      capacity: usize, // Maximum length of the chunks read from the underlying reader
+      eof: bool, // Whether the underlying reader has reported EOF
+      raw: Range<u64>, // Offsets in the underlying reader of the chunk the buffered data was mapped from
+  }
+}
+
+impl<'a, R: AsyncRead> AsyncVecMapReader<'a, R> {
+    /// Create a new wrapper around an async reader with a processing function
+    pub fn new(reader: R, process_fn: impl MapReadFnVec + 'a) -> Self {
+        Self::with_capacity(reader, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Create a new wrapper reading chunks of at most `capacity` bytes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(reader: R, process_fn: impl MapReadFnVec + 'a, capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be at least 1");
+        Self {
+            inner: reader,
+            process_fn: Box::new(process_fn),
+            buf: Vec::with_capacity(capacity),
+            pos: 0,
+            capacity,
+            eof: false,
+            raw: 0..0,
+        }
+    }
+
+    /// Returns the range of offsets in the underlying reader of the chunk the buffered data was mapped from.
+    ///
+    /// This relates the mapped data back to the original data, e.g. to report the position of an error
+    /// in the original file. The range is empty before the first chunk is read and at EOF.
+    pub fn current_raw_range(&self) -> Range<u64> {
+        self.raw.clone()
+    }
+
+    /// Consume the wrapper and return the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for AsyncVecMapReader<'_, R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let rem = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let amt = std::cmp::min(rem.len(), buf.len());
+        buf[..amt].copy_from_slice(&rem[..amt]);
+        self.consume(amt);
+        Poll::Ready(Ok(amt))
+    }
+}
+
+impl<R: AsyncRead> AsyncBufRead for AsyncVecMapReader<'_, R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let mut this = self.project();
+        // Read and map chunks until one produces some data, or EOF
+        while *this.pos >= this.buf.len() && !*this.eof && *this.capacity > 0 {
+            *this.pos = 0;
+            this.buf.resize(*this.capacity, 0);
+            let read_amount = match this.inner.as_mut().poll_read(cx, this.buf) {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(e)) => {
+                    this.buf.clear();
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => {
+                    this.buf.clear();
+                    return Poll::Pending;
+                }
+            };
+            this.buf.truncate(read_amount);
+            *this.raw = this.raw.end..this.raw.end + read_amount as u64;
+            if read_amount == 0 {
+                *this.eof = true;
+                break;
+            }
+            (this.process_fn).map_read(this.buf);
+        }
+        Poll::Ready(Ok(&this.buf[*this.pos..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.project();
+        *this.pos = std::cmp::min(*this.pos + amt, this.buf.len());
+    }
+}
@@ -0,0 +1,57 @@
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::MapWriteFn;
+
+/// A write mapping function compressing the data with gzip.
+///
+/// Each chunk is fed to the compressor, and whatever compressed data it produced so far is written out.
+/// When the writer is closed, the compressor is finished, writing the final deflate block and the gzip trailer,
+/// so the writer must be closed for the output to be a complete gzip stream.
+pub struct GzipEncodeWrite {
+    encoder: GzEncoder<Vec<u8>>,
+}
+
+impl GzipEncodeWrite {
+    /// Creates a new `GzipEncodeWrite` with the default compression level.
+    pub fn new() -> Self {
+        Self::with_level(Compression::default())
+    }
+
+    /// Creates a new `GzipEncodeWrite` with the given compression level.
+    pub fn with_level(level: Compression) -> Self {
+        Self {
+            encoder: GzEncoder::new(Vec::new(), level),
+        }
+    }
+
+    /// Replaces `buf` with the compressed data produced so far.
+    fn take_output(&mut self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.append(self.encoder.get_mut());
+    }
+}
+
+impl Default for GzipEncodeWrite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MapWriteFn for GzipEncodeWrite {
+    fn map_write(&mut self, buf: &mut Vec<u8>) {
+        self.encoder
+            .write_all(buf)
+            .expect("compressing into a Vec can't fail");
+        self.take_output(buf);
+    }
+
+    fn map_write_final(&mut self, buf: &mut Vec<u8>) {
+        self.encoder
+            .write_all(buf)
+            .and_then(|()| self.encoder.try_finish())
+            .expect("compressing into a Vec can't fail");
+        self.take_output(buf);
+    }
+}
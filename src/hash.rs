@@ -0,0 +1,96 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncRead};
+
+use crate::{AsyncMapReader, MapReadFn, TryMapReadFn};
+
+/// A hash function producing a 32 byte digest, e.g. SHA-256 or BLAKE3.
+pub trait ContentHasher {
+    /// Feeds `data` to the hash function.
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the digest of all the data fed so far.
+    fn finalize(&mut self) -> [u8; 32];
+}
+
+pin_project_lite::pin_project! {
+  /// An `AsyncRead` hashing the data served by an [`AsyncMapReader`], and checking it against
+  /// an expected hash at EOF.
+  ///
+  /// If the hash doesn't match, the read that would have reported EOF fails with an
+  /// [`io::ErrorKind::InvalidData`] error instead.
+  /// This is usually constructed through [`AsyncMapReader::with_expected_hash`].
+  pub struct ExpectedHash<'a, R, F = Box<dyn TryMapReadFn + 'a>> {
+      #[pin]
+      inner: AsyncMapReader<'a, R, F>,
+      hasher: Box<dyn ContentHasher + 'a>,
+      expected: [u8; 32],
+      checked: bool, // Whether EOF was reached and the hash checked
+  }
+}
+
+impl<'a, R: AsyncRead, F: TryMapReadFn> ExpectedHash<'a, R, F> {
+    /// Creates a new `ExpectedHash` checking the data served by `inner` against `expected`.
+    pub fn new(
+        inner: AsyncMapReader<'a, R, F>,
+        expected: [u8; 32],
+        hasher: impl ContentHasher + 'a,
+    ) -> Self {
+        Self {
+            inner,
+            hasher: Box::new(hasher),
+            expected,
+            checked: false,
+        }
+    }
+
+    /// Consumes the `ExpectedHash` and returns the wrapped reader.
+    pub fn into_inner(self) -> AsyncMapReader<'a, R, F> {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead, F: TryMapReadFn> AsyncRead for ExpectedHash<'_, R, F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if *this.checked {
+            return Poll::Ready(Ok(0));
+        }
+        let n = ready!(this.inner.poll_read(cx, buf))?;
+        if n > 0 {
+            this.hasher.update(&buf[..n]);
+            return Poll::Ready(Ok(n));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        *this.checked = true;
+        if this.hasher.finalize() != *this.expected {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "hash of the mapped data doesn't match the expected hash",
+            )));
+        }
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncMapReader<'a, R> {
+    /// Create a new wrapper hashing the mapped data with `hasher`,
+    /// and failing at EOF if the hash isn't `expected`, see [`ExpectedHash`].
+    pub fn with_expected_hash(
+        reader: R,
+        process_fn: impl MapReadFn + 'a,
+        expected: [u8; 32],
+        hasher: impl ContentHasher + 'a,
+    ) -> ExpectedHash<'a, R> {
+        ExpectedHash::new(Self::new(reader, process_fn), expected, hasher)
+    }
+}
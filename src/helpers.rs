@@ -0,0 +1,43 @@
+use futures_lite::{io, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{AsyncMapReader, MapReadFn};
+
+/// Reads everything from `src` through the mapping function and returns the result as a `String`.
+///
+/// Returns an [`io::ErrorKind::InvalidData`] error if the mapped data is not valid UTF-8.
+pub async fn map_to_string<R: AsyncRead>(src: R, process_fn: impl MapReadFn) -> io::Result<String> {
+    let reader = AsyncMapReader::new(src, process_fn);
+    futures_lite::pin!(reader);
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).await?;
+    String::from_utf8(out).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Copies everything from `src` through the mapping function into `dst`, and flushes `dst`.
+///
+/// `progress` is called with the total number of bytes copied so far after every chunk is written,
+/// e.g. to drive a progress bar. Returns the total number of bytes copied.
+pub async fn copy_mapped_with_progress<R: AsyncRead, W: AsyncWrite>(
+    src: R,
+    dst: W,
+    process_fn: impl MapReadFn,
+    mut progress: impl FnMut(u64),
+) -> io::Result<u64> {
+    let reader = AsyncMapReader::from_fn(src, process_fn);
+    futures_lite::pin!(reader);
+    futures_lite::pin!(dst);
+    let mut total = 0;
+    loop {
+        let chunk = reader.fill_buf().await?;
+        if chunk.is_empty() {
+            break;
+        }
+        let len = chunk.len();
+        dst.write_all(chunk).await?;
+        reader.as_mut().consume(len);
+        total += len as u64;
+        progress(total);
+    }
+    dst.flush().await?;
+    Ok(total)
+}
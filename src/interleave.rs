@@ -0,0 +1,116 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, AsyncRead};
+
+use crate::{AsyncMapReader, MapReadFn};
+
+pin_project_lite::pin_project! {
+  /// An `AsyncRead` interleaving the bytes of two readers at a fixed ratio.
+  ///
+  /// With a ratio of `(a, b)`, `a` bytes are read from the first reader, then `b` bytes from the second,
+  /// and so on. Once one of the readers reaches EOF, the rest of the other one is read on its own.
+  /// This is usually constructed through [`AsyncMapReader::interleave`].
+  pub struct Interleave<A, B> {
+      #[pin]
+      a: A,
+      #[pin]
+      b: B,
+      ratio: (usize, usize),
+      on_b: bool, // Whether the current turn is the second reader's
+      remaining: usize, // Bytes left to read in the current turn
+      a_eof: bool,
+      b_eof: bool,
+  }
+}
+
+impl<A: AsyncRead, B: AsyncRead> Interleave<A, B> {
+    /// Creates a new `Interleave` reading `ratio.0` bytes from `a`, then `ratio.1` bytes from `b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either side of the ratio is zero.
+    pub fn new(a: A, b: B, ratio: (usize, usize)) -> Self {
+        assert!(
+            ratio.0 > 0 && ratio.1 > 0,
+            "both sides of the ratio must be at least 1"
+        );
+        Self {
+            a,
+            b,
+            ratio,
+            on_b: false,
+            remaining: ratio.0,
+            a_eof: false,
+            b_eof: false,
+        }
+    }
+
+    /// Consumes the `Interleave` and returns the underlying readers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+impl<A: AsyncRead, B: AsyncRead> AsyncRead for Interleave<A, B> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        loop {
+            if *this.a_eof && *this.b_eof {
+                return Poll::Ready(Ok(0));
+            }
+            // Once a reader is done, drain the other one without taking turns
+            let (on_b, limit) = if *this.a_eof {
+                (true, buf.len())
+            } else if *this.b_eof {
+                (false, buf.len())
+            } else {
+                (*this.on_b, std::cmp::min(buf.len(), *this.remaining))
+            };
+            let res = if on_b {
+                this.b.as_mut().poll_read(cx, &mut buf[..limit])
+            } else {
+                this.a.as_mut().poll_read(cx, &mut buf[..limit])
+            };
+            let n = match res {
+                Poll::Ready(Ok(n)) => n,
+                other => return other,
+            };
+            if n == 0 && limit > 0 {
+                if on_b {
+                    *this.b_eof = true;
+                } else {
+                    *this.a_eof = true;
+                }
+                continue;
+            }
+            if !*this.a_eof && !*this.b_eof {
+                *this.remaining -= n;
+                if *this.remaining == 0 {
+                    *this.on_b = !*this.on_b;
+                    *this.remaining = if *this.on_b {
+                        this.ratio.1
+                    } else {
+                        this.ratio.0
+                    };
+                }
+            }
+            return Poll::Ready(Ok(n));
+        }
+    }
+}
+
+impl<'a, A: AsyncRead, B: AsyncRead> AsyncMapReader<'a, Interleave<A, B>> {
+    /// Create a new wrapper over the bytes of `a` and `b` interleaved at `ratio`, see [`Interleave`].
+    ///
+    /// The mapping function is applied to the interleaved stream.
+    pub fn interleave(a: A, b: B, ratio: (usize, usize), process_fn: impl MapReadFn + 'a) -> Self {
+        Self::new(Interleave::new(a, b, ratio), process_fn)
+    }
+}
@@ -1,7 +1,70 @@
+mod adaptive;
+mod broadcast;
+mod buffer;
+mod cipher;
+#[cfg(feature = "tokio")]
+mod compat;
+mod demux;
+mod dispatch;
+mod emit;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "fs")]
+mod fs;
+mod growable;
+#[cfg(feature = "flate2")]
+mod gzip;
+mod hash;
+mod helpers;
+mod interleave;
+mod lines;
+mod padding;
+mod poll_map;
 mod read;
+mod replay;
+mod reverse;
+mod shared;
+mod sink;
+mod stats;
+#[cfg(feature = "stdio")]
+mod stdio;
+mod streaming;
+#[cfg(feature = "sync")]
+mod sync;
+mod verify;
 mod write;
 
+pub use adaptive::*;
+pub use broadcast::*;
+pub use cipher::*;
+#[cfg(feature = "tokio")]
+pub use compat::*;
+pub use demux::*;
+pub use dispatch::*;
+pub use emit::*;
+#[cfg(feature = "encoding")]
+pub use encoding::*;
+#[cfg(feature = "fs")]
+pub use fs::*;
+pub use growable::*;
+#[cfg(feature = "flate2")]
+pub use gzip::*;
+pub use hash::*;
+pub use helpers::*;
+pub use interleave::*;
+pub use lines::*;
+pub use padding::*;
+pub use poll_map::*;
 pub use read::*;
+pub use replay::*;
+pub use reverse::*;
+pub use shared::*;
+pub use sink::*;
+pub use stats::*;
+pub use streaming::*;
+#[cfg(feature = "sync")]
+pub use sync::*;
+pub use verify::*;
 pub use write::*;
 
 #[cfg(test)]
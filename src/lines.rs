@@ -0,0 +1,119 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncRead};
+
+use crate::{AsyncMapReader, DEFAULT_BUFFER_SIZE};
+
+type PredicateFn<'a> = Box<dyn FnMut(&[u8]) -> bool + 'a>;
+type LineFn<'a> = Box<dyn FnMut(&mut Vec<u8>) + 'a>;
+
+pin_project_lite::pin_project! {
+  /// An `AsyncRead` mapping only the lines of the underlying reader that match a predicate.
+  ///
+  /// Lines are split on `\n`, and passed to the predicate and the mapping function without it.
+  /// Lines that don't match are served unchanged. Lines are buffered whole, however long they are,
+  /// and the last line is mapped at EOF even if it doesn't end with a newline.
+  /// This is usually constructed through [`AsyncMapReader::map_matching_lines`].
+  pub struct MatchingLines<'a, R> {
+      #[pin]
+      inner: R,
+      predicate: PredicateFn<'a>,
+      line_fn: LineFn<'a>,
+      raw: Vec<u8>, // Buffer for reading from the underlying reader
+      line: Vec<u8>, // Start of the current line, until its end is read
+      out: Vec<u8>, // Complete lines ready to be served
+      pos: usize, // Position of the next byte to serve in `out`
+      eof: bool, // Whether the underlying reader has reported EOF
+  }
+}
+
+impl<'a, R: AsyncRead> MatchingLines<'a, R> {
+    /// Creates a new `MatchingLines` applying `line_fn` to the lines of `reader` matching `predicate`.
+    pub fn new(
+        reader: R,
+        predicate: impl FnMut(&[u8]) -> bool + 'a,
+        line_fn: impl FnMut(&mut Vec<u8>) + 'a,
+    ) -> Self {
+        Self {
+            inner: reader,
+            predicate: Box::new(predicate),
+            line_fn: Box::new(line_fn),
+            raw: vec![0; DEFAULT_BUFFER_SIZE],
+            line: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Consume the wrapper and return the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for MatchingLines<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        loop {
+            if *this.pos < this.out.len() {
+                let amt = std::cmp::min(this.out.len() - *this.pos, buf.len());
+                buf[..amt].copy_from_slice(&this.out[*this.pos..*this.pos + amt]);
+                *this.pos += amt;
+                return Poll::Ready(Ok(amt));
+            }
+            if *this.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            this.out.clear();
+            *this.pos = 0;
+            let read_amount = ready!(this.inner.as_mut().poll_read(cx, this.raw))?;
+            if read_amount == 0 {
+                *this.eof = true;
+                if !this.line.is_empty() {
+                    let mut line = std::mem::take(this.line);
+                    if (this.predicate)(&line) {
+                        (this.line_fn)(&mut line);
+                    }
+                    this.out.extend_from_slice(&line);
+                }
+                continue;
+            }
+            for piece in this.raw[..read_amount].split_inclusive(|&b| b == b'\n') {
+                this.line.extend_from_slice(piece);
+                if this.line.last() != Some(&b'\n') {
+                    // The rest of the line is still to be read
+                    break;
+                }
+                this.line.pop();
+                if (this.predicate)(this.line) {
+                    (this.line_fn)(this.line);
+                }
+                this.out.extend_from_slice(this.line);
+                this.out.push(b'\n');
+                this.line.clear();
+            }
+        }
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncMapReader<'a, R> {
+    /// Create a [`MatchingLines`] applying `line_fn` only to the lines of `reader` matching `predicate`.
+    ///
+    /// The `MatchingLines` buffers the lines itself, so it's returned as is rather than wrapped again.
+    pub fn map_matching_lines(
+        reader: R,
+        predicate: impl FnMut(&[u8]) -> bool + 'a,
+        line_fn: impl FnMut(&mut Vec<u8>) + 'a,
+    ) -> MatchingLines<'a, R> {
+        MatchingLines::new(reader, predicate, line_fn)
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, AsyncRead};
+
+use crate::{MapWriteFn, DEFAULT_BUFFER_SIZE};
+
+/// A write mapping function that applies PKCS#7 padding before an inner mapping function.
+///
+/// Every chunk passed to the inner function is a multiple of the block size:
+/// bytes that don't fill a whole block are held back and prepended to the next chunk.
+/// When the writer is closed, the remaining bytes are padded to a full block
+/// (adding a whole block of padding if they already were a multiple of the block size),
+/// so the padding can always be removed again with an [`UnpadReader`].
+pub struct PaddingTransform<F> {
+    inner: F,
+    block_size: usize,
+    carry: Vec<u8>, // Bytes held back until a full block is available
+}
+
+impl<F: MapWriteFn> PaddingTransform<F> {
+    /// Creates a new `PaddingTransform` padding to `block_size` bytes before applying `inner`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not between 1 and 255, the range supported by PKCS#7.
+    pub fn new(block_size: usize, inner: F) -> Self {
+        assert!(
+            (1..=255).contains(&block_size),
+            "block size must be between 1 and 255"
+        );
+        Self {
+            inner,
+            block_size,
+            carry: Vec::new(),
+        }
+    }
+
+    /// Prepends the held back bytes to `buf`.
+    fn take_carry(&mut self, buf: &mut Vec<u8>) {
+        // Copy rather than swap, so the buffer keeps its own allocation
+        buf.splice(..0, self.carry.drain(..));
+    }
+}
+
+impl<F: MapWriteFn> MapWriteFn for PaddingTransform<F> {
+    fn map_write(&mut self, buf: &mut Vec<u8>) {
+        self.take_carry(buf);
+        let aligned = buf.len() - buf.len() % self.block_size;
+        self.carry.extend_from_slice(&buf[aligned..]);
+        buf.truncate(aligned);
+        if !buf.is_empty() {
+            self.inner.map_write(buf);
+        }
+    }
+
+    fn map_write_final(&mut self, buf: &mut Vec<u8>) {
+        self.take_carry(buf);
+        let padding = self.block_size - buf.len() % self.block_size;
+        buf.resize(buf.len() + padding, padding as u8);
+        self.inner.map_write(buf);
+    }
+}
+
+pin_project_lite::pin_project! {
+  /// A reader that removes the PKCS#7 padding added by a [`PaddingTransform`].
+  ///
+  /// The last block read from the underlying reader is held back until EOF,
+  /// at which point its padding is validated and stripped.
+  /// Invalid padding is reported as an [`io::ErrorKind::InvalidData`] error.
+  pub struct UnpadReader<R> {
+      #[pin]
+      inner: R,
+      block_size: usize,
+      buf: Vec<u8>, // Data read from the underlying reader
+      pos: usize, // Position of the next byte to serve in `buf`
+      eof: bool, // Whether the padding has been stripped
+  }
+}
+
+impl<R: AsyncRead> UnpadReader<R> {
+    /// Creates a new `UnpadReader` removing padding to `block_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not between 1 and 255, the range supported by PKCS#7.
+    pub fn new(reader: R, block_size: usize) -> Self {
+        assert!(
+            (1..=255).contains(&block_size),
+            "block size must be between 1 and 255"
+        );
+        Self {
+            inner: reader,
+            block_size,
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Consume the wrapper and return the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Validates and removes the PKCS#7 padding of the final block in `buf`.
+fn strip_padding(buf: &mut Vec<u8>, block_size: usize) -> io::Result<()> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid padding");
+    if buf.len() != block_size {
+        return Err(invalid());
+    }
+    let padding = buf[buf.len() - 1] as usize;
+    if padding == 0 || padding > block_size {
+        return Err(invalid());
+    }
+    let data_len = buf.len() - padding;
+    if buf[data_len..].iter().any(|&b| b as usize != padding) {
+        return Err(invalid());
+    }
+    buf.truncate(data_len);
+    Ok(())
+}
+
+impl<R: AsyncRead> AsyncRead for UnpadReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        loop {
+            // Everything but the last block can be served before EOF
+            let servable = if *this.eof {
+                this.buf.len()
+            } else {
+                this.buf.len().saturating_sub(*this.block_size)
+            };
+            if *this.pos < servable {
+                let amt = std::cmp::min(servable - *this.pos, out.len());
+                out[..amt].copy_from_slice(&this.buf[*this.pos..*this.pos + amt]);
+                *this.pos += amt;
+                return Poll::Ready(Ok(amt));
+            }
+            if *this.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            this.buf.drain(..*this.pos);
+            *this.pos = 0;
+            let start = this.buf.len();
+            this.buf.resize(start + DEFAULT_BUFFER_SIZE, 0);
+            let read = this.inner.as_mut().poll_read(cx, &mut this.buf[start..]);
+            let read_amount = match read {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(e)) => {
+                    this.buf.truncate(start);
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => {
+                    this.buf.truncate(start);
+                    return Poll::Pending;
+                }
+            };
+            this.buf.truncate(start + read_amount);
+            if read_amount == 0 {
+                strip_padding(this.buf, *this.block_size)?;
+                *this.eof = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncBufRead, AsyncRead};
+
+use crate::{buffer::Buffer, DEFAULT_BUFFER_SIZE};
+
+/// A trait for mapping data read from an underlying reader, with access to the reader itself.
+///
+/// This is an advanced version of [`MapReadFn`](crate::MapReadFn) for mapping functions that need to
+/// wait on asynchronous resources, or pull auxiliary data from the underlying reader mid-chunk.
+pub trait PollMapReadFn<R> {
+    /// Applies a mapping function to the data read from the underlying reader.
+    ///
+    /// `inner` is the underlying reader the chunk was read from; any data read from it here
+    /// is consumed by the mapping function and never served by the [`AsyncPollMapReader`].
+    ///
+    /// If this returns `Poll::Pending` or an error, it will be called again with the same chunk
+    /// on the next read, so the mapping function is responsible for remembering how much of
+    /// the chunk it has already mapped.
+    fn poll_map_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        inner: Pin<&mut R>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<()>>;
+}
+
+pin_project_lite::pin_project! {
+  /// A wrapper around an `AsyncRead` applying a [`PollMapReadFn`] to the data read.
+  ///
+  /// This behaves like [`AsyncMapReader`](crate::AsyncMapReader), except the mapping function
+  /// can suspend the read and access the underlying reader while mapping a chunk.
+  pub struct AsyncPollMapReader<'a, R> {
+      #[pin]
+      inner: R,
+      process_fn: Box<dyn PollMapReadFn<R> + 'a>,
+      pos: usize, // Current position in the buffer
+      cap: usize, // Current position and capacity of the buffer
+      buf: Buffer, // Internal buffer for reading data
+      mapping: bool, // Whether `buf[..cap]` is still waiting on the mapping function
+      eof: bool, // Whether the underlying reader has reported EOF
+  }
+}
+
+impl<'a, R: AsyncRead> AsyncPollMapReader<'a, R> {
+    /// Create a new wrapper around an async reader with a processing function
+    pub fn new(reader: R, process_fn: impl PollMapReadFn<R> + 'a) -> Self {
+        Self::with_capacity(reader, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Create a new wrapper with a specific initial buffer capacity
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(
+        reader: R,
+        process_fn: impl PollMapReadFn<R> + 'a,
+        capacity: usize,
+    ) -> Self {
+        assert!(capacity > 0, "capacity must be at least 1");
+        Self {
+            inner: reader,
+            process_fn: Box::new(process_fn),
+            pos: 0,
+            cap: 0,
+            buf: Buffer::aligned(capacity, 1),
+            mapping: false,
+            eof: false,
+        }
+    }
+
+    /// Consume the wrapper and return the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for AsyncPollMapReader<'_, R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let rem = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let amt = std::cmp::min(rem.len(), buf.len());
+        buf[..amt].copy_from_slice(&rem[..amt]);
+        self.consume(amt);
+        Poll::Ready(Ok(amt))
+    }
+}
+
+impl<R: AsyncRead> AsyncBufRead for AsyncPollMapReader<'_, R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let mut this = self.project();
+        if !*this.mapping && *this.pos >= *this.cap {
+            *this.pos = 0;
+            *this.cap = 0;
+            if *this.eof {
+                return Poll::Ready(Ok(&[]));
+            }
+            let read_amount = ready!(this.inner.as_mut().poll_read(cx, this.buf))?;
+            if read_amount == 0 {
+                *this.eof = true;
+                return Poll::Ready(Ok(&[]));
+            }
+            *this.cap = read_amount;
+            *this.mapping = true;
+        }
+        if *this.mapping {
+            let chunk = &mut this.buf[..*this.cap];
+            ready!(this
+                .process_fn
+                .poll_map_read(cx, this.inner.as_mut(), chunk))?;
+            *this.mapping = false;
+        }
+        Poll::Ready(Ok(&this.buf[*this.pos..*this.cap]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.project();
+        *this.pos = std::cmp::min(*this.pos + amt, *this.cap);
+    }
+}
@@ -1,8 +1,23 @@
-use crate::DEFAULT_BUFFER_SIZE;
-use futures_lite::{io, ready, AsyncBufRead, AsyncRead};
+use crate::{buffer::Buffer, MapStats, DEFAULT_BUFFER_SIZE};
+use futures_lite::{io, ready, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncSeek};
+use std::fmt;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::task::{Context, Poll};
 
+/// Copies the kind and message of an error, which can't be cloned.
+pub(crate) fn copy_error(e: &io::Error) -> io::Error {
+    io::Error::new(e.kind(), e.to_string())
+}
+
+type ValidateFn<'a> = Arc<Mutex<dyn FnMut(&[u8]) -> io::Result<()> + Send + 'a>>;
+type ProgressFn<'a> = Arc<Mutex<dyn FnMut(usize) + Send + 'a>>;
+type SegmentFn<'a> = Arc<Mutex<dyn FnMut(u64, &[u8]) + Send + 'a>>;
+
+/// The byte the unused part of the buffer is filled with in debug builds, before mapping a chunk.
+#[cfg(debug_assertions)]
+pub(crate) const POISON: u8 = 0xDB;
+
 /// A trait for mapping data read from an underlying reader.
 pub trait MapReadFn {
     /// Applies a mapping function to the data read from the underlying reader.
@@ -11,6 +26,179 @@ pub trait MapReadFn {
     /// The length of the buffer is gauranteed to be equal to the capacity of the underlying
     /// buffer, until the last read operation, where it may be smaller.
     fn map_read(&mut self, buf: &mut [u8]);
+
+    /// Applies the mapping function, unless the chunk ends in the middle of a unit it can't map on its own.
+    ///
+    /// Returning [`MapReadStatus::Incomplete`] (without modifying `buf`) makes the reader read more data,
+    /// growing its buffer up to [`AsyncMapReader::with_max_capacity`] if needed,
+    /// and call this again with the longer chunk. The default implementation always maps the chunk
+    /// with [`MapReadFn::map_read`].
+    fn map_read_checked(&mut self, buf: &mut [u8]) -> MapReadStatus {
+        self.map_read(buf);
+        MapReadStatus::Complete
+    }
+
+    /// Returns a size that every chunk (but the last) must be a multiple of, e.g. the block size of a cipher.
+    ///
+    /// The reader's capacity is rounded up to a multiple of this when it's created.
+    /// The default implementation returns 1, so any capacity is accepted.
+    fn required_multiple(&self) -> usize {
+        1
+    }
+
+    /// Called once with the first chunk, right before it's passed to [`MapReadFn::map_read`].
+    ///
+    /// This allows the mapping function to configure itself from the start of the stream,
+    /// e.g. from a format version. The default implementation does nothing.
+    fn init(&mut self, first_chunk: &[u8]) {
+        let _ = first_chunk;
+    }
+
+    /// Reports whether the mapping function is falling behind and would like the source to slow down.
+    ///
+    /// This is purely cooperative: the reader never pauses on its own, it only surfaces the signal
+    /// through [`AsyncMapReader::backpressure`]. The default implementation never signals backpressure.
+    fn backpressure(&self) -> BackpressureSignal {
+        BackpressureSignal::None
+    }
+
+    /// Called once the underlying reader reports EOF, after the last chunk was mapped.
+    ///
+    /// Anything appended to `out` is served after the mapped data (and before the terminator, if any),
+    /// e.g. a footer built from what the mapping function has seen. The default implementation does nothing.
+    fn finalize(&mut self, out: &mut Vec<u8>) {
+        let _ = out;
+    }
+}
+
+/// A trait for mapping data read from an underlying reader, where the mapping can fail.
+///
+/// Every [`MapReadFn`] is also a `TryMapReadFn` that never fails and keeps the whole chunk,
+/// so the infallible mapping functions keep working with [`AsyncMapReader::try_new`].
+pub trait TryMapReadFn {
+    /// Applies a fallible mapping function to the data read from the underlying reader, in place.
+    ///
+    /// Returns how many bytes at the start of `buf` are valid; the rest of the chunk is dropped.
+    /// If this fails, the error is returned by the read that triggered the mapping, and the whole chunk
+    /// is discarded, so a retried read carries on with the following data instead of mapping it twice.
+    fn try_map_read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Like [`TryMapReadFn::try_map_read`], but returns `Ok(None)` (without modifying `buf`)
+    /// if the chunk ends in the middle of a unit, see [`MapReadFn::map_read_checked`].
+    ///
+    /// The default implementation always maps the chunk with [`TryMapReadFn::try_map_read`].
+    fn try_map_read_checked(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        self.try_map_read(buf).map(Some)
+    }
+
+    /// Like [`TryMapReadFn::try_map_read_checked`], but may only consume the start of the chunk,
+    /// see [`MapReadFnStateful`].
+    ///
+    /// Returns how many bytes are valid and how many were consumed; the valid bytes are taken from the start
+    /// of the consumed ones, and the bytes that weren't consumed are passed again at the start of the next chunk.
+    /// Consuming nothing is the same as returning `Ok(None)` from [`TryMapReadFn::try_map_read_checked`].
+    /// The default implementation always consumes the whole chunk.
+    fn try_map_read_carry(&mut self, buf: &mut [u8]) -> io::Result<Option<(usize, usize)>> {
+        let len = buf.len();
+        Ok(self.try_map_read_checked(buf)?.map(|valid| (valid, len)))
+    }
+
+    /// See [`MapReadFn::required_multiple`].
+    fn required_multiple(&self) -> usize {
+        1
+    }
+
+    /// See [`MapReadFn::init`].
+    fn init(&mut self, first_chunk: &[u8]) {
+        let _ = first_chunk;
+    }
+
+    /// See [`MapReadFn::backpressure`].
+    fn backpressure(&self) -> BackpressureSignal {
+        BackpressureSignal::None
+    }
+
+    /// See [`MapReadFn::finalize`].
+    fn finalize(&mut self, out: &mut Vec<u8>) {
+        let _ = out;
+    }
+}
+
+impl<T: MapReadFn> TryMapReadFn for T {
+    fn try_map_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.map_read(buf);
+        Ok(buf.len())
+    }
+
+    fn try_map_read_checked(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        Ok(match self.map_read_checked(buf) {
+            MapReadStatus::Complete => Some(buf.len()),
+            MapReadStatus::Incomplete => None,
+        })
+    }
+
+    fn required_multiple(&self) -> usize {
+        MapReadFn::required_multiple(self)
+    }
+
+    fn init(&mut self, first_chunk: &[u8]) {
+        MapReadFn::init(self, first_chunk)
+    }
+
+    fn backpressure(&self) -> BackpressureSignal {
+        MapReadFn::backpressure(self)
+    }
+
+    fn finalize(&mut self, out: &mut Vec<u8>) {
+        MapReadFn::finalize(self, out)
+    }
+}
+
+/// A signal raised by a [`MapReadFn`] to ask whoever is feeding the source to slow down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackpressureSignal {
+    /// The mapping function is keeping up.
+    #[default]
+    None,
+    /// The mapping function is falling behind.
+    SlowDown,
+}
+
+/// Whether [`MapReadFn::map_read_checked`] could map a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapReadStatus {
+    /// The chunk was mapped.
+    Complete,
+    /// The chunk ends with an incomplete unit, and was left untouched.
+    ///
+    /// If no more data can be read (at EOF, or if the buffer can't grow any more), the read fails.
+    Incomplete,
+}
+
+/// A position in the data buffered by an [`AsyncMapReader`], see [`AsyncMapReader::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferedSnapshot {
+    pos: usize,
+    cap: usize,
+    fills: u64,
+}
+
+/// How an [`AsyncMapReader`] splits the data of the underlying reader into chunks for the mapping function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkPolicy {
+    /// Every chunk is exactly this many bytes, except the last one before EOF (or before an error).
+    ///
+    /// Reads are buffered across fills until a whole chunk is available.
+    Fixed(usize),
+    /// Every chunk is exactly what a single read of the underlying reader returned.
+    ///
+    /// This has the lowest latency, but chunk sizes depend entirely on the underlying reader.
+    RawReads,
+    /// Every chunk is at least this many bytes, except the last one before EOF (or before an error).
+    ///
+    /// Reads are buffered across fills until enough data is available,
+    /// and a chunk is mapped as soon as it reaches this size.
+    MinChunk(usize),
 }
 
 impl<F> MapReadFn for F
@@ -22,6 +210,170 @@ where
     }
 }
 
+/// A trait for mapping data read from an underlying reader in whole units, carrying partial units over.
+///
+/// This is meant for mapping functions working on groups of bytes (e.g. base64 quads or fixed-size records)
+/// that may be split across reads.
+pub trait MapReadFnStateful {
+    /// Maps the start of `buf` in place, and returns how many bytes were mapped.
+    ///
+    /// Only `buf[..n]` is served; the unmapped tail `buf[n..]` is passed again at the start of the next chunk.
+    /// Returning 0 makes the reader read more data, and fails the read if no more data is coming,
+    /// like [`MapReadStatus::Incomplete`].
+    fn map_read(&mut self, buf: &mut [u8]) -> usize;
+}
+
+impl<F> MapReadFnStateful for F
+where
+    F: FnMut(&mut [u8]) -> usize,
+{
+    fn map_read(&mut self, buf: &mut [u8]) -> usize {
+        self(buf)
+    }
+}
+
+/// Adapts a [`MapReadFnStateful`] to the reader.
+struct Stateful<F>(F);
+
+impl TryMapReadFn for Box<dyn TryMapReadFn + '_> {
+    fn try_map_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).try_map_read(buf)
+    }
+
+    fn try_map_read_checked(&mut self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        (**self).try_map_read_checked(buf)
+    }
+
+    fn try_map_read_carry(&mut self, buf: &mut [u8]) -> io::Result<Option<(usize, usize)>> {
+        (**self).try_map_read_carry(buf)
+    }
+
+    fn required_multiple(&self) -> usize {
+        (**self).required_multiple()
+    }
+
+    fn init(&mut self, first_chunk: &[u8]) {
+        (**self).init(first_chunk)
+    }
+
+    fn backpressure(&self) -> BackpressureSignal {
+        (**self).backpressure()
+    }
+
+    fn finalize(&mut self, out: &mut Vec<u8>) {
+        (**self).finalize(out)
+    }
+}
+
+impl<F: MapReadFnStateful> TryMapReadFn for Stateful<F> {
+    fn try_map_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.0.map_read(buf))
+    }
+
+    fn try_map_read_carry(&mut self, buf: &mut [u8]) -> io::Result<Option<(usize, usize)>> {
+        let consumed = self.0.map_read(buf);
+        Ok(Some((consumed, consumed)))
+    }
+}
+
+/// Passes the last `overlap` unmapped bytes of the stream to the mapping function ahead of each chunk.
+struct Overlap<F> {
+    process_fn: F,
+    overlap: usize,
+    context: Vec<u8>, // Last unmapped bytes of the previous chunks
+    scratch: Vec<u8>, // Context followed by the current chunk, as passed to the mapping function
+}
+
+impl<F: MapReadFn> MapReadFn for Overlap<F> {
+    fn map_read(&mut self, buf: &mut [u8]) {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&self.context);
+        self.scratch.extend_from_slice(buf);
+        let context_len = self.context.len();
+
+        self.context.extend_from_slice(buf);
+        let excess = self.context.len().saturating_sub(self.overlap);
+        self.context.drain(..excess);
+
+        MapReadFn::map_read(&mut self.process_fn, &mut self.scratch);
+        buf.copy_from_slice(&self.scratch[context_len..]);
+    }
+
+    fn required_multiple(&self) -> usize {
+        MapReadFn::required_multiple(&self.process_fn)
+    }
+
+    fn init(&mut self, first_chunk: &[u8]) {
+        MapReadFn::init(&mut self.process_fn, first_chunk)
+    }
+
+    fn backpressure(&self) -> BackpressureSignal {
+        MapReadFn::backpressure(&self.process_fn)
+    }
+
+    fn finalize(&mut self, out: &mut Vec<u8>) {
+        MapReadFn::finalize(&mut self.process_fn, out)
+    }
+}
+
+/// Passes the last `len` mapped bytes to the mapping function along with each chunk.
+struct OutputHistory<F> {
+    process_fn: F,
+    len: usize,
+    history: Vec<u8>, // Last mapped bytes of the previous chunks
+}
+
+impl<F: FnMut(&[u8], &mut [u8])> MapReadFn for OutputHistory<F> {
+    fn map_read(&mut self, buf: &mut [u8]) {
+        (self.process_fn)(&self.history, buf);
+        self.history.extend_from_slice(buf);
+        let excess = self.history.len().saturating_sub(self.len);
+        self.history.drain(..excess);
+    }
+}
+
+/// Splits the served data into segments of `size` bytes, passed to a callback along with their index.
+#[derive(Clone)]
+struct Segmenter<'a> {
+    size: usize,
+    index: u64,       // Index of the next segment
+    pending: Vec<u8>, // Served bytes not making up a whole segment yet
+    callback: SegmentFn<'a>,
+}
+
+impl Segmenter<'_> {
+    fn push(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let amt = std::cmp::min(self.size - self.pending.len(), data.len());
+            self.pending.extend_from_slice(&data[..amt]);
+            data = &data[amt..];
+            if self.pending.len() == self.size {
+                self.emit();
+            }
+        }
+    }
+
+    /// Starts over from the first segment, dropping the bytes not passed to the callback yet.
+    fn reset(&mut self) {
+        self.index = 0;
+        self.pending.clear();
+    }
+
+    /// Passes the last, shorter segment to the callback, if there is one.
+    fn finish(&mut self) {
+        if !self.pending.is_empty() {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        let mut callback = self.callback.lock().unwrap_or_else(PoisonError::into_inner);
+        callback(self.index, &self.pending);
+        self.index += 1;
+        self.pending.clear();
+    }
+}
+
 pin_project_lite::pin_project! {
   /// A wrapper around an `AsyncRead` that allows for data processing
   /// before the actual I/O operation.
@@ -33,67 +385,845 @@ pin_project_lite::pin_project! {
   /// The buffer size also acts as a threshold for the length of data passed to the mapping function,
   /// and will be gauranteed to be equal the capacity of the underlying buffer, until the last read operation,
   /// where it may be smaller.
-  pub struct AsyncMapReader<'a, R> {
+  /// Small reads from the underlying reader are accumulated until the buffer is full,
+  /// so a chunk is only shorter if the underlying reader has no more data available without blocking.
+  ///
+  /// Once the underlying reader reports EOF (and the terminator, if any, has been served), the wrapper
+  /// is fused: every subsequent read returns `Ok(0)` without polling the underlying reader or invoking the mapping function again.
+  ///
+  /// The mapping function is boxed by default, see [`AsyncMapReader::from_fn`] and [`AsyncMapRead::map`]
+  /// to call it without the allocation and dynamic dispatch.
+  pub struct AsyncMapReader<'a, R, F = Box<dyn TryMapReadFn + 'a>> {
       #[pin]
       inner: R,
-      process_fn: Box<dyn MapReadFn + 'a>,
+      process_fn: F,
       pos: usize, // Current position in the buffer
       cap: usize, // Current position and capacity of the buffer
-      buf: Box<[u8]>, // Internal buffer for reading data
+      buf: Buffer, // Internal buffer for reading data
+      eof: bool, // Whether the underlying reader has reported EOF
+      invocations: u64, // Number of times the mapping function has been called
+      enabled: bool, // Whether the mapping function is applied
+      deferred_error: Option<io::Error>, // Error to report once the buffered data is consumed
+      recorded: Option<Vec<usize>>, // Lengths of the chunks passed to the mapping function, if recording
+      stats: Option<MapStats>, // Statistics, if enabled
+      terminator: Vec<u8>, // Bytes served after the underlying reader reports EOF
+      terminator_pos: usize, // How much of the terminator has been served
+      finalized: bool, // Whether the mapping function's `finalize` hook has been called
+      footer_len: usize, // Length of the footer from the `finalize` hook, at the front of the terminator
+      window: Option<usize>, // Exact length of the chunks passed to the mapping function, if set
+      align: usize, // Length the chunks passed to the mapping function must be a multiple of, but the last one
+      held: usize, // Bytes read after `cap` that are waiting for a whole window or chunk
+      policy: Option<ChunkPolicy>, // How reads are split into chunks, if not the default coalescing
+      initialized: bool, // Whether the mapping function's `init` hook has been called
+      byte_counter: Option<(u8, u64)>, // Byte to count in the served data, and its count so far
+      ascii_guard: bool, // Whether to fail on non-ASCII mapped data
+      validator: Option<ValidateFn<'a>>, // Check run on every mapped chunk before it's served
+      fills: u64, // Number of times the buffer has been refilled, to invalidate snapshots
+      max_capacity: usize, // Size the buffer may grow to for incomplete units
+      max_retries: Option<usize>, // How many times in a row the mapping function may report an incomplete chunk
+      retries: usize, // How many times in a row the mapping function has reported an incomplete chunk
+      last_error: Option<io::Error>, // Copy of the last error returned
+      progress: Option<ProgressFn<'a>>, // Called with the length of every read from the underlying reader
+      limit: Option<u64>, // How many more mapped bytes may be served, if limited
+      limit_total: Option<u64>, // Limit set with `with_limit`, restored when seeking
+      segmenter: Option<Segmenter<'a>>, // Receives the served data in segments, if set
   }
 }
 
+/// An [`AsyncMapReader`] with a boxed mapping function, as created by its constructors.
+pub type BoxedAsyncMapReader<'a, R> = AsyncMapReader<'a, R, Box<dyn TryMapReadFn + 'a>>;
+
 impl<'a, R> AsyncMapReader<'a, R>
 where
     R: AsyncRead,
 {
     /// Create a new wrapper around an async reader with a processing function
     pub fn new(reader: R, process_fn: impl MapReadFn + 'a) -> Self {
-        Self {
-            inner: reader,
-            process_fn: Box::new(process_fn),
-            pos: 0,
-            cap: 0,
-            buf: vec![0; DEFAULT_BUFFER_SIZE].into_boxed_slice(), // Start with a reasonable capacity
-        }
+        Self::with_capacity(reader, process_fn, DEFAULT_BUFFER_SIZE)
     }
 
     /// Create a new wrapper with a specific initial buffer capacity
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
     pub fn with_capacity(reader: R, process_fn: impl MapReadFn + 'a, capacity: usize) -> Self {
+        Self::with_aligned_capacity(reader, process_fn, capacity, 1)
+    }
+
+    /// Create a new wrapper whose internal buffer starts at a multiple of `align` bytes
+    ///
+    /// Every chunk passed to the mapping function starts at the beginning of the internal buffer,
+    /// which allows mapping functions to use aligned (e.g. SIMD) loads and stores.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn with_aligned_capacity(
+        reader: R,
+        process_fn: impl MapReadFn + 'a,
+        capacity: usize,
+        align: usize,
+    ) -> Self {
+        Self::from_parts(reader, Box::new(process_fn), capacity, align)
+    }
+
+    /// Create a new wrapper around an async reader with a fallible processing function
+    ///
+    /// Errors from the mapping function are returned by the read that triggered them,
+    /// see [`TryMapReadFn::try_map_read`].
+    pub fn try_new(reader: R, process_fn: impl TryMapReadFn + 'a) -> Self {
+        Self::try_with_capacity(reader, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Create a new wrapper with a fallible processing function and a specific initial buffer capacity
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn try_with_capacity(
+        reader: R,
+        process_fn: impl TryMapReadFn + 'a,
+        capacity: usize,
+    ) -> Self {
+        Self::from_parts(reader, Box::new(process_fn), capacity, 1)
+    }
+
+    /// Create a new wrapper mapping whole units with a [`MapReadFnStateful`]
+    ///
+    /// Bytes the mapping function leaves unmapped are carried over to the next chunk,
+    /// so it never sees a partial unit, unless the stream ends with one.
+    pub fn new_stateful(reader: R, process_fn: impl MapReadFnStateful + 'a) -> Self {
+        Self::stateful_with_capacity(reader, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Create a new wrapper mapping whole units with a specific initial buffer capacity
+    ///
+    /// The capacity must be large enough to hold at least one unit, see [`AsyncMapReader::with_max_capacity`].
+    pub fn stateful_with_capacity(
+        reader: R,
+        process_fn: impl MapReadFnStateful + 'a,
+        capacity: usize,
+    ) -> Self {
+        Self::from_parts(reader, Box::new(Stateful(process_fn)), capacity, 1)
+    }
+
+    /// Create a new wrapper serving `prefilled` before any data from the underlying reader.
+    ///
+    /// This is useful to resume a stream after some of it has already been read, e.g. during a handshake.
+    /// If `already_transformed` is `false`, the prefilled bytes are passed to the mapping function
+    /// along with the first data read from the underlying reader; otherwise they are served as they are.
+    pub fn with_prefill_buffer(
+        reader: R,
+        process_fn: impl MapReadFn + 'a,
+        prefilled: Vec<u8>,
+        already_transformed: bool,
+    ) -> Self {
+        let capacity = std::cmp::max(prefilled.len(), DEFAULT_BUFFER_SIZE);
+        let mut this = Self::with_capacity(reader, process_fn, capacity);
+        this.buf[..prefilled.len()].copy_from_slice(&prefilled);
+        if already_transformed {
+            this.cap = prefilled.len();
+        } else {
+            // Treat the bytes as if they were read, but not mapped yet
+            this.held = prefilled.len();
+        }
+        this
+    }
+
+    /// Create a new wrapper counting the occurrences of `target` in the mapped data.
+    ///
+    /// Only the data actually served is counted, see [`AsyncMapReader::count`].
+    pub fn with_byte_counter(reader: R, target: u8, process_fn: impl MapReadFn + 'a) -> Self {
+        Self {
+            byte_counter: Some((target, 0)),
+            ..Self::new(reader, process_fn)
+        }
+    }
+
+    /// Create a new wrapper applying `f` to every byte read.
+    pub fn map_bytes(reader: R, mut f: impl FnMut(u8) -> u8 + 'a) -> Self {
+        Self::new(reader, move |buf: &mut [u8]| {
+            buf.iter_mut().for_each(|b| *b = f(*b))
+        })
+    }
+
+    /// Create a new wrapper applying the fallible `f` to every byte read,
+    /// substituting `replacement` for every byte it fails on.
+    ///
+    /// This suits lenient pipelines that prefer marking invalid data over aborting the stream.
+    pub fn with_replacement(
+        reader: R,
+        replacement: u8,
+        mut f: impl FnMut(u8) -> io::Result<u8> + 'a,
+    ) -> Self {
+        Self::new(reader, move |buf: &mut [u8]| {
+            buf.iter_mut()
+                .for_each(|b| *b = f(*b).unwrap_or(replacement))
+        })
+    }
+
+    /// Create a new wrapper that only passes chunks whose length is a multiple of `align`
+    /// to the mapping function, except for the last one at EOF (or before an error).
+    ///
+    /// Trailing bytes that don't make up a whole group are held back and prepended to the next chunk,
+    /// so alignment groups (e.g. base64 quads) are never split. The capacity is rounded up to a multiple of `align`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is zero or larger than `capacity`.
+    pub fn with_alignment(
+        reader: R,
+        process_fn: impl MapReadFn + 'a,
+        capacity: usize,
+        align: usize,
+    ) -> Self {
+        Self::aligned_groups(reader, Box::new(process_fn), capacity, align)
+    }
+
+    /// Create a new wrapper that only maps full buffers of `capacity` bytes, except at EOF or on an error.
+    ///
+    /// Unlike the default, a chunk isn't mapped early when the underlying reader would block,
+    /// so size-sensitive mapping functions see the same chunks however the data arrives.
+    /// This trades latency for determinism, see also [`ChunkPolicy::MinChunk`].
+    pub fn with_capacity_filled(
+        reader: R,
+        process_fn: impl MapReadFn + 'a,
+        capacity: usize,
+    ) -> Self {
+        let this = Self::with_capacity(reader, process_fn, capacity);
+        Self {
+            policy: Some(ChunkPolicy::MinChunk(this.capacity())),
+            ..this
+        }
+    }
+
+    /// Create a new wrapper splitting the data into chunks according to `policy`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of a [`ChunkPolicy::Fixed`] or [`ChunkPolicy::MinChunk`] policy is zero.
+    pub fn with_chunk_policy(
+        reader: R,
+        process_fn: impl MapReadFn + 'a,
+        policy: ChunkPolicy,
+    ) -> Self {
+        Self::try_with_chunk_policy(reader, process_fn, policy)
+    }
+
+    /// Create a new wrapper with a fallible processing function, splitting the data into chunks according to `policy`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of a [`ChunkPolicy::Fixed`] or [`ChunkPolicy::MinChunk`] policy is zero.
+    pub fn try_with_chunk_policy(
+        reader: R,
+        process_fn: impl TryMapReadFn + 'a,
+        policy: ChunkPolicy,
+    ) -> Self {
+        let (capacity, window) = match policy {
+            ChunkPolicy::Fixed(size) => {
+                assert!(size > 0, "chunk size must be at least 1");
+                (size, Some(size))
+            }
+            ChunkPolicy::RawReads => (DEFAULT_BUFFER_SIZE, None),
+            ChunkPolicy::MinChunk(size) => {
+                assert!(size > 0, "chunk size must be at least 1");
+                (std::cmp::max(size, DEFAULT_BUFFER_SIZE), None)
+            }
+        };
+        Self {
+            window,
+            policy: Some(policy),
+            ..Self::try_with_capacity(reader, process_fn, capacity)
+        }
+    }
+
+    /// Create a new wrapper that passes exactly `window_size` bytes to each call of the mapping function.
+    ///
+    /// Reads are buffered across fills until a whole window is available,
+    /// so only the final window before EOF (or before an error) may be shorter.
+    /// This suits mapping functions that reorder bytes within a fixed window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window_size` is zero.
+    pub fn with_window(reader: R, window_size: usize, process_fn: impl MapReadFn + 'a) -> Self {
+        assert!(window_size > 0, "window size must be at least 1");
+        let capacity = std::cmp::max(
+            DEFAULT_BUFFER_SIZE - DEFAULT_BUFFER_SIZE % window_size,
+            window_size,
+        );
+        Self {
+            window: Some(window_size),
+            ..Self::with_capacity(reader, process_fn, capacity)
+        }
+    }
+
+    /// Create a new wrapper that passes the last `overlap` bytes of the previous chunks
+    /// to the mapping function ahead of each chunk, as context.
+    ///
+    /// The context bytes are the original bytes read, not their mapped output, and only the mapped
+    /// chunk itself is served, so nothing is duplicated. The mapping function must not change the
+    /// length of the data. This suits mapping functions where each output byte depends on the
+    /// bytes before it, like smoothing filters. The first chunk is passed without context.
+    pub fn with_overlap(reader: R, overlap: usize, process_fn: impl MapReadFn + 'a) -> Self {
+        Self::new(
+            reader,
+            Overlap {
+                process_fn,
+                overlap,
+                context: Vec::with_capacity(overlap),
+                scratch: Vec::new(),
+            },
+        )
+    }
+
+    /// Create a new wrapper that passes the last `history_len` mapped bytes to the mapping function,
+    /// as read-only context along with each chunk.
+    ///
+    /// Unlike [`AsyncMapReader::with_overlap`], the context is the output of the mapping function,
+    /// for mappings where each byte depends on the bytes produced before it.
+    /// The history is shorter than `history_len` until that many bytes have been mapped.
+    pub fn with_output_history(
+        reader: R,
+        history_len: usize,
+        process_fn: impl FnMut(&[u8], &mut [u8]) + 'a,
+    ) -> Self {
+        Self::new(
+            reader,
+            OutputHistory {
+                process_fn,
+                len: history_len,
+                history: Vec::with_capacity(history_len),
+            },
+        )
+    }
+
+    /// Create a new wrapper that serves `terminator` once the underlying reader reports EOF.
+    ///
+    /// The terminator is not passed to the mapping function, and is served exactly once,
+    /// right before EOF, even if the underlying reader is empty.
+    pub fn with_terminator(
+        reader: R,
+        terminator: Vec<u8>,
+        process_fn: impl MapReadFn + 'a,
+    ) -> Self {
+        Self {
+            terminator,
+            ..Self::new(reader, process_fn)
+        }
+    }
+}
+
+impl<'a, R, F> AsyncMapReader<'a, R, F>
+where
+    R: AsyncRead,
+    F: TryMapReadFn,
+{
+    /// Create a new wrapper calling `process_fn` directly, without boxing it
+    ///
+    /// This avoids an allocation and dynamic dispatch for every chunk, at the cost of
+    /// naming the type of the mapping function in the type of the wrapper, see [`BoxedAsyncMapReader`].
+    pub fn from_fn(reader: R, process_fn: F) -> Self {
+        Self::from_fn_with_capacity(reader, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Create a new wrapper calling `process_fn` directly, with a specific initial buffer capacity
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn from_fn_with_capacity(reader: R, process_fn: F, capacity: usize) -> Self {
+        Self::from_parts(reader, process_fn, capacity, 1)
+    }
+
+    fn from_parts(reader: R, process_fn: F, capacity: usize, align: usize) -> Self {
+        assert!(capacity > 0, "capacity must be at least 1");
+        let capacity = capacity.next_multiple_of(process_fn.required_multiple().max(1));
         Self {
             inner: reader,
-            process_fn: Box::new(process_fn),
+            process_fn,
             pos: 0,
             cap: 0,
-            buf: vec![0; capacity].into_boxed_slice(),
+            buf: Buffer::aligned(capacity, align),
+            eof: false,
+            invocations: 0,
+            enabled: true,
+            deferred_error: None,
+            recorded: None,
+            stats: None,
+            terminator: Vec::new(),
+            terminator_pos: 0,
+            finalized: false,
+            footer_len: 0,
+            window: None,
+            align: 1,
+            held: 0,
+            policy: None,
+            initialized: false,
+            byte_counter: None,
+            ascii_guard: false,
+            validator: None,
+            fills: 0,
+            max_capacity: capacity,
+            max_retries: None,
+            retries: 0,
+            last_error: None,
+            progress: None,
+            limit: None,
+            limit_total: None,
+            segmenter: None,
+        }
+    }
+
+    fn aligned_groups(reader: R, process_fn: F, capacity: usize, align: usize) -> Self {
+        assert!(align > 0, "alignment must be at least 1");
+        assert!(align <= capacity, "alignment must not exceed the capacity");
+        Self {
+            align,
+            ..Self::from_parts(reader, process_fn, capacity.next_multiple_of(align), 1)
         }
     }
 
+    /// Returns the capacity of the internal buffer.
+    ///
+    /// This may be larger than requested, see [`MapReadFn::required_multiple`].
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns a copy of the last error returned by this reader, if any.
+    ///
+    /// The copy keeps the kind and message of the error, but not its source.
+    /// This lets a supervisor inspect a failure after the error itself was handled elsewhere.
+    pub fn last_error(&self) -> Option<&io::Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Returns the number of mapped bytes buffered and not consumed yet.
+    pub fn buffered(&self) -> usize {
+        self.cap - self.pos
+    }
+
+    /// Returns the part of the buffer after the current chunk.
+    #[cfg(test)]
+    pub(crate) fn unused_buffer(&self) -> &[u8] {
+        &self.buf[self.cap + self.held..]
+    }
+
+    /// Returns `true` if the mapping function currently signals backpressure.
+    ///
+    /// See [`MapReadFn::backpressure`].
+    pub fn backpressure(&self) -> bool {
+        self.process_fn.backpressure() == BackpressureSignal::SlowDown
+    }
+
+    /// Returns the number of times the mapping function has been called.
+    ///
+    /// This is the number of chunks read from the underlying reader so far.
+    pub fn transform_invocations(&self) -> u64 {
+        self.invocations
+    }
+
+    /// Start recording the length of every chunk passed to the mapping function.
+    ///
+    /// The recorded lengths can be retrieved with [`AsyncMapReader::recorded_chunks`],
+    /// and passed to [`AsyncMapReader::replay_chunks`] to reproduce the exact same chunk boundaries.
+    pub fn record_chunks(mut self) -> Self {
+        self.recorded = Some(Vec::new());
+        self
+    }
+
+    /// Returns the lengths of the chunks passed to the mapping function so far.
+    ///
+    /// This is empty unless [`AsyncMapReader::record_chunks`] was called.
+    pub fn recorded_chunks(&self) -> &[usize] {
+        self.recorded.as_deref().unwrap_or_default()
+    }
+
+    /// Start collecting statistics about this reader, see [`AsyncMapReader::stats`].
+    pub fn with_stats(mut self) -> Self {
+        self.stats = Some(MapStats::default());
+        self
+    }
+
+    /// Allow the buffer to grow up to `max_capacity` bytes
+    /// when the mapping function reports an incomplete chunk, see [`MapReadFn::map_read_checked`].
+    ///
+    /// By default the buffer never grows.
+    pub fn with_max_capacity(mut self, max_capacity: usize) -> Self {
+        self.max_capacity = max_capacity;
+        self
+    }
+
+    /// Wipe the internal buffer with zeros when the reader is dropped,
+    /// or when the buffer is reallocated to grow.
+    ///
+    /// This keeps sensitive mapped data, like decrypted plaintext, from lingering in freed memory.
+    /// Data already served, or returned by [`AsyncMapReader::into_parts`], isn't covered.
+    #[cfg(feature = "zeroize")]
+    pub fn with_zeroize(mut self) -> Self {
+        self.buf.zeroize = true;
+        self
+    }
+
+    /// Fail reads with an [`io::ErrorKind::Other`] error once the mapping function reports
+    /// an incomplete chunk more than `n` times in a row, see [`MapReadFn::map_read_checked`].
+    ///
+    /// This guards against mapping functions that never make progress, however much data they're given.
+    /// By default there is no limit, other than the maximum capacity of the buffer.
+    pub fn max_transform_retries(mut self, n: usize) -> Self {
+        self.max_retries = Some(n);
+        self
+    }
+
+    /// Fail reads with an [`io::ErrorKind::InvalidData`] error if the mapped data isn't all ASCII.
+    ///
+    /// This catches mapping functions that are meant to produce text but accidentally emit high bytes.
+    /// The offending chunk is discarded.
+    pub fn with_ascii_guard(mut self) -> Self {
+        self.ascii_guard = true;
+        self
+    }
+
+    /// Run `validator` on every mapped chunk before serving it.
+    ///
+    /// If the validator returns an error, the read fails with it and the offending chunk is discarded,
+    /// like with [`AsyncMapReader::with_ascii_guard`].
+    /// The validator must be `Send`, so the reader can still be moved to another thread.
+    pub fn validate(mut self, validator: impl FnMut(&[u8]) -> io::Result<()> + Send + 'a) -> Self {
+        self.validator = Some(Arc::new(Mutex::new(validator)));
+        self
+    }
+
+    /// Call `progress` after every successful read from the underlying reader,
+    /// with the number of bytes just read, before they're mapped.
+    ///
+    /// This counts the original data, even if the mapping function shrinks it, e.g. to drive a download progress bar.
+    /// The callback must be `Send`, so the reader can still be moved to another thread.
+    pub fn on_progress(mut self, progress: impl FnMut(usize) + Send + 'a) -> Self {
+        self.progress = Some(Arc::new(Mutex::new(progress)));
+        self
+    }
+
+    /// Serve at most `limit` bytes, then report EOF.
+    ///
+    /// Like [`AsyncReadExt::take`](futures_lite::AsyncReadExt::take), but the limit counts
+    /// the mapped bytes actually served, so it holds even if the mapping function changes the length of the data.
+    /// Data read past the limit stays buffered and is dropped along with the reader.
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self.limit_total = Some(limit);
+        self
+    }
+
+    /// Call `segment` with every `segment_size` bytes of mapped data served, along with the index of the segment.
+    ///
+    /// The reads themselves are unaffected, unlike with [`AsyncMapReader::with_chunk_policy`]:
+    /// the data is passed to `segment` as it's consumed, e.g. to upload each segment as one part.
+    /// The last segment is shorter if the data runs out first, and is passed once EOF is reached.
+    /// The callback must be `Send`, so the reader can still be moved to another thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `segment_size` is zero.
+    pub fn with_segmenter(
+        mut self,
+        segment_size: usize,
+        segment: impl FnMut(u64, &[u8]) + Send + 'a,
+    ) -> Self {
+        assert!(segment_size > 0, "segment size must be at least 1");
+        self.segmenter = Some(Segmenter {
+            size: segment_size,
+            index: 0,
+            pending: Vec::with_capacity(segment_size),
+            callback: Arc::new(Mutex::new(segment)),
+        });
+        self
+    }
+
+    /// Returns the statistics collected since [`AsyncMapReader::with_stats`] was called.
+    ///
+    /// If statistics aren't enabled, only [`MapStats::transform_invocations`] is filled in.
+    pub fn stats(&self) -> MapStats {
+        MapStats {
+            transform_invocations: self.invocations,
+            ..self.stats.unwrap_or_default()
+        }
+    }
+
+    /// Returns how many times the byte given to [`AsyncMapReader::with_byte_counter`]
+    /// has been served so far, after mapping.
+    ///
+    /// This is always 0 if the reader wasn't created with a byte counter.
+    pub fn count(&self) -> u64 {
+        self.byte_counter.map_or(0, |(_, count)| count)
+    }
+
+    /// Captures the current position in the buffered data, to go back to it with [`AsyncMapReader::restore`].
+    pub fn snapshot(&self) -> BufferedSnapshot {
+        BufferedSnapshot {
+            pos: self.pos,
+            cap: self.cap,
+            fills: self.fills,
+        }
+    }
+
+    /// Rolls the reader back to `snapshot`, so the data consumed since is served again.
+    ///
+    /// This only works within the currently buffered data: if the buffer has been refilled
+    /// since the snapshot was taken, this does nothing and returns `false`.
+    /// Data served again is counted again by [`AsyncMapReader::stats`] and [`AsyncMapReader::count`].
+    pub fn restore(&mut self, snapshot: BufferedSnapshot) -> bool {
+        if snapshot.fills != self.fills || snapshot.cap != self.cap {
+            return false;
+        }
+        self.pos = snapshot.pos;
+        true
+    }
+
+    /// Enables or disables the mapping function.
+    ///
+    /// While disabled, data is still buffered but passes through unchanged.
+    /// Changing this only affects chunks read afterwards: data that is already buffered
+    /// is served as it is.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns `true` if the mapping function is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
     /// Consume the wrapper and return the inner reader
+    ///
+    /// Any mapped bytes still buffered and not yet consumed are discarded,
+    /// use [`AsyncMapReader::into_parts`] to recover them.
     pub fn into_inner(self) -> R {
         self.inner
     }
+
+    /// Consume the wrapper and return the inner reader, along with the mapped bytes
+    /// that were buffered but not yet consumed.
+    ///
+    /// Reading the returned bytes and then the inner reader resumes where the wrapper stopped,
+    /// except for bytes read ahead and held back to complete a window or an aligned group,
+    /// which haven't been mapped yet.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        let buffered = self.buf[self.pos..self.cap].to_vec();
+        (self.inner, buffered)
+    }
+
+    /// Consume the wrapper and return the mapping function, e.g. to recover the state it kept.
+    ///
+    /// The underlying reader and any buffered data are discarded.
+    pub fn into_process_fn(self) -> F {
+        self.process_fn
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading from the underlying reader directly skips the data this wrapper has already
+    /// buffered (and mapped), so the two get out of sync.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Gets a pinned mutable reference to the underlying reader.
+    ///
+    /// This does not require `R: Unpin`. The same caveat as [`AsyncMapReader::get_mut`] applies.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut R> {
+        self.project().inner
+    }
+}
+
+/// Cloning is only available when both the underlying reader and the mapping function are `Clone`,
+/// so not for the boxed mapping function of [`BoxedAsyncMapReader`].
+///
+/// The clone has its own copy of the buffered data, and reads independently from the same position.
+/// A validator set with [`AsyncMapReader::validate`], and callbacks set with [`AsyncMapReader::on_progress`]
+/// and [`AsyncMapReader::with_segmenter`], are shared between the clones,
+/// and an error deferred until the buffered data is consumed is copied with its kind and message only.
+impl<'a, R: Clone, F: Clone> Clone for AsyncMapReader<'a, R, F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            process_fn: self.process_fn.clone(),
+            pos: self.pos,
+            cap: self.cap,
+            buf: self.buf.clone(),
+            eof: self.eof,
+            invocations: self.invocations,
+            enabled: self.enabled,
+            deferred_error: self.deferred_error.as_ref().map(copy_error),
+            recorded: self.recorded.clone(),
+            stats: self.stats,
+            terminator: self.terminator.clone(),
+            terminator_pos: self.terminator_pos,
+            finalized: self.finalized,
+            footer_len: self.footer_len,
+            window: self.window,
+            align: self.align,
+            held: self.held,
+            policy: self.policy,
+            initialized: self.initialized,
+            byte_counter: self.byte_counter,
+            ascii_guard: self.ascii_guard,
+            validator: self.validator.clone(),
+            fills: self.fills,
+            max_capacity: self.max_capacity,
+            max_retries: self.max_retries,
+            retries: self.retries,
+            last_error: self.last_error.as_ref().map(copy_error),
+            progress: self.progress.clone(),
+            limit: self.limit,
+            limit_total: self.limit_total,
+            segmenter: self.segmenter.clone(),
+        }
+    }
+}
+
+impl<R: fmt::Debug, F> fmt::Debug for AsyncMapReader<'_, R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncMapReader")
+            .field("inner", &self.inner)
+            .field("capacity", &self.buf.len())
+            .field("pos", &self.pos)
+            .field("cap", &self.cap)
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> AsyncMapReader<'a, &'a [u8]> {
+    /// Create a new wrapper over data that is already in memory, e.g. a memory-mapped file.
+    ///
+    /// The data is mapped one chunk at a time as it's read,
+    /// so only a buffer's worth of it is ever copied at once.
+    pub fn from_mapped(data: &'a [u8], process_fn: impl MapReadFn + 'a) -> Self {
+        Self::new(data, process_fn)
+    }
+}
+
+impl<R: AsyncRead + Unpin, F: TryMapReadFn> AsyncMapReader<'_, R, F> {
+    /// Skips over `n` mapped bytes, without copying them anywhere.
+    ///
+    /// The mapping function is still applied to the skipped data, as chunks are mapped as a whole.
+    /// Returns the number of bytes skipped, which is less than `n` if EOF was reached.
+    pub async fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let mut skipped = 0;
+        while skipped < n {
+            let available = self.fill_buf().await?.len();
+            if available == 0 {
+                break;
+            }
+            let amt = std::cmp::min(available as u64, n - skipped);
+            AsyncBufReadExt::consume(self, amt as usize);
+            skipped += amt;
+        }
+        Ok(skipped)
+    }
+
+    /// Returns the next `n` mapped bytes without consuming them.
+    ///
+    /// Fills the buffer as many times as needed, keeping the data not consumed yet, so the next read
+    /// still starts with the peeked bytes. Fewer than `n` bytes are returned if the stream ends first,
+    /// if the underlying reader fails (the error is reported once the buffered data is consumed),
+    /// or if `n` is larger than the capacity, in which case the whole buffer is returned.
+    pub async fn peek(&mut self, n: usize) -> io::Result<&[u8]> {
+        futures_lite::future::poll_fn(|cx| {
+            let mut this = Pin::new(&mut *self);
+            ready!(this.as_mut().poll_fill_buf(cx))?;
+            while this.pos < this.cap && this.cap - this.pos < std::cmp::min(n, this.buf.len()) {
+                let available = this.cap - this.pos;
+                ready!(this.as_mut().poll_refill(cx))?;
+                if this.cap - this.pos == available {
+                    break;
+                }
+            }
+            Poll::Ready(Ok::<_, io::Error>(()))
+        })
+        .await?;
+        let available = if self.pos < self.cap {
+            &self.buf[self.pos..self.cap]
+        } else {
+            &self.terminator[self.terminator_pos..]
+        };
+        let available = limited(available, self.limit);
+        Ok(&available[..std::cmp::min(n, available.len())])
+    }
+
+    /// Reads exactly `buf.len()` mapped bytes.
+    ///
+    /// Unlike `read_exact`, this keeps filling the buffer as long as the underlying reader isn't at EOF,
+    /// even when a fill produces no mapped data, so it only fails with [`io::ErrorKind::UnexpectedEof`]
+    /// if the underlying reader runs out before enough mapped bytes were produced.
+    pub async fn read_exact_transformed(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let data = match self.fill_buf().await {
+                Ok(data) => data,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if data.is_empty() {
+                if self.eof || self.limit == Some(0) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "underlying reader ended before enough mapped data was produced",
+                    ));
+                }
+                continue;
+            }
+            let amt = std::cmp::min(data.len(), buf.len() - filled);
+            buf[filled..filled + amt].copy_from_slice(&data[..amt]);
+            AsyncBufReadExt::consume(self, amt);
+            filled += amt;
+        }
+        Ok(())
+    }
+
+    /// Reads and maps everything until EOF or the first error, appending the data to `out`.
+    ///
+    /// Unlike `read_to_end`, an error doesn't discard the progress made so far:
+    /// returns the number of bytes appended to `out`, along with the error that stopped the read, if any.
+    pub async fn read_to_end_lossy(&mut self, out: &mut Vec<u8>) -> (usize, Option<io::Error>) {
+        let start = out.len();
+        loop {
+            match self.fill_buf().await {
+                Ok([]) => break,
+                Ok(data) => {
+                    let amt = data.len();
+                    out.extend_from_slice(data);
+                    AsyncBufReadExt::consume(self, amt);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return (out.len() - start, Some(e)),
+            }
+        }
+        (out.len() - start, None)
+    }
 }
 
-impl<'a, R> AsyncRead for AsyncMapReader<'a, R>
+impl<R, F> AsyncRead for AsyncMapReader<'_, R, F>
 where
     R: AsyncRead,
+    F: TryMapReadFn,
 {
+    #[inline]
     fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        if self.pos == self.cap {
-            let fill = ready!(self.as_mut().poll_fill_buf(cx))?;
-            if fill.is_empty() {
-                return Poll::Ready(Ok(0));
-            }
-        }
-        let rem = {
-            let this = self.as_mut().project();
-            &this.buf[*this.pos..*this.cap]
-        };
+        let rem = ready!(self.as_mut().poll_fill_buf(cx))?;
         let amt = std::cmp::min(rem.len(), buf.len());
         buf[..amt].copy_from_slice(&rem[..amt]);
         self.consume(amt);
@@ -101,26 +1231,343 @@ where
     }
 }
 
-impl<'a, R: AsyncRead> AsyncBufRead for AsyncMapReader<'a, R> {
-    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+impl<R: AsyncRead, F: TryMapReadFn> AsyncMapReader<'_, R, F> {
+    /// Refills the buffer, reading and mapping a new chunk after the data not consumed yet.
+    ///
+    /// This is usually called once everything has been consumed, and is kept out of `poll_fill_buf`,
+    /// so serving already buffered data stays cheap.
+    fn poll_refill(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let res = ready!(self.as_mut().poll_map_chunk(cx));
+        if let Err(e) = &res {
+            *self.project().last_error = Some(copy_error(e));
+        }
+        Poll::Ready(res)
+    }
+
+    /// Reads and maps a new chunk for [`AsyncMapReader::poll_refill`].
+    fn poll_map_chunk(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         let mut this = self.project();
-        if *this.pos >= *this.cap {
-            debug_assert!(*this.pos == *this.cap);
+        loop {
+            // Move the mapped data still to be served, and the bytes held back from the previous fill,
+            // to the front of the buffer
+            let base = *this.cap - *this.pos;
+            let mut read_amount = base + std::mem::take(this.held);
+            this.buf.copy_within(*this.pos..*this.pos + read_amount, 0);
             *this.pos = 0;
-            *this.cap = 0;
-            let read_amount = ready!(this.inner.as_mut().poll_read(cx, this.buf))?;
-            if read_amount == 0 {
-                return Poll::Ready(Ok(&[]));
+            *this.cap = base;
+            if *this.eof && read_amount == base {
+                return Poll::Ready(Ok(()));
+            }
+            if this.deferred_error.is_some() {
+                if base > 0 {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(this.deferred_error.take().unwrap()));
+            }
+            // Keep reading until the buffer is full or the underlying reader would block,
+            // so tiny reads don't each cost a call to the mapping function.
+            let mut pending = false;
+            while read_amount < this.buf.len() && !*this.eof {
+                match this
+                    .inner
+                    .as_mut()
+                    .poll_read(cx, &mut this.buf[read_amount..])
+                {
+                    Poll::Ready(Ok(0)) => {
+                        if let Some(stats) = this.stats {
+                            stats.inner_calls += 1;
+                        }
+                        *this.eof = true;
+                        break;
+                    }
+                    Poll::Ready(Ok(n)) => {
+                        read_amount += n;
+                        if let Some(progress) = this.progress {
+                            (progress.lock().unwrap_or_else(PoisonError::into_inner))(n);
+                        }
+                        if let Some(stats) = this.stats {
+                            stats.bytes_in += n as u64;
+                            stats.inner_calls += 1;
+                        }
+                        match *this.policy {
+                            Some(ChunkPolicy::RawReads) => break,
+                            Some(ChunkPolicy::MinChunk(min)) if read_amount >= min => break,
+                            _ => {}
+                        }
+                    }
+                    Poll::Ready(Err(e)) if read_amount == 0 => return Poll::Ready(Err(e)),
+                    Poll::Ready(Err(e)) => {
+                        // Serve what was read so far, and report the error on the next fill
+                        *this.deferred_error = Some(e);
+                        break;
+                    }
+                    Poll::Pending if read_amount == base => return Poll::Pending,
+                    Poll::Pending => {
+                        pending = true;
+                        break;
+                    }
+                }
+            }
+            // Only whole windows (or big enough chunks) are mapped, unless no more data is coming
+            if pending {
+                let fresh = read_amount - base;
+                *this.held = match (*this.window, *this.policy) {
+                    (Some(window), _) => fresh % window,
+                    (_, Some(ChunkPolicy::MinChunk(min))) if fresh < min => fresh,
+                    _ if *this.align > 1 => fresh % *this.align,
+                    _ => 0,
+                };
+                read_amount -= *this.held;
+                if read_amount == base {
+                    return Poll::Pending;
+                }
+            }
+            if read_amount == base {
+                return Poll::Ready(Ok(()));
+            }
+            // Make mapping functions that read past their chunk easy to spot
+            #[cfg(debug_assertions)]
+            this.buf[read_amount + *this.held..].fill(POISON);
+            let mut mapped = read_amount;
+            if *this.enabled {
+                let step = this.window.unwrap_or(read_amount - base);
+                let mut incomplete = false;
+                let mut failed = None;
+                mapped = base;
+                for start in (base..read_amount).step_by(step) {
+                    let end = std::cmp::min(start + step, read_amount);
+                    let chunk = &mut this.buf[start..end];
+                    if !*this.initialized {
+                        (this.process_fn).init(chunk);
+                        *this.initialized = true;
+                    }
+                    let (valid, consumed) = match (this.process_fn).try_map_read_carry(chunk) {
+                        Ok(Some((valid, consumed))) if consumed > 0 || start > base => {
+                            let consumed = std::cmp::min(consumed, chunk.len());
+                            (std::cmp::min(valid, consumed), consumed)
+                        }
+                        Ok(_) => {
+                            incomplete = true;
+                            break;
+                        }
+                        Err(e) => {
+                            failed = Some(e);
+                            break;
+                        }
+                    };
+                    *this.invocations += 1;
+                    if let Some(stats) = this.stats {
+                        stats.record_chunk(chunk.len());
+                    }
+                    if let Some(recorded) = this.recorded {
+                        recorded.push(chunk.len());
+                    }
+                    // Pack the valid output of each chunk together
+                    this.buf.copy_within(start..start + valid, mapped);
+                    mapped += valid;
+                    if consumed < end - start {
+                        // Carry the rest over to the next fill, ahead of the bytes already held back
+                        *this.held += read_amount - (start + consumed);
+                        read_amount = start + consumed;
+                        break;
+                    }
+                }
+                if let Some(e) = failed {
+                    // Drop the whole fill, but keep the bytes held back for the next one
+                    this.buf
+                        .copy_within(read_amount..read_amount + *this.held, base);
+                    return Poll::Ready(Err(e));
+                }
+                if incomplete {
+                    *this.retries += 1;
+                    if this.max_retries.is_some_and(|max| *this.retries > max) {
+                        return Poll::Ready(Err(io::Error::other(
+                            "mapping function made no progress within the maximum number of retries",
+                        )));
+                    }
+                    // Read more data into the buffer and try again
+                    if *this.eof || this.deferred_error.is_some() {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "incomplete unit at the end of the stream",
+                        )));
+                    }
+                    if this.window.is_some() || read_amount >= *this.max_capacity {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "unit doesn't fit in the maximum buffer capacity",
+                        )));
+                    }
+                    *this.held += read_amount - base;
+                    if pending {
+                        return Poll::Pending;
+                    }
+                    if read_amount == this.buf.len() {
+                        let len = std::cmp::min(read_amount * 2, *this.max_capacity);
+                        this.buf.resize(len);
+                    }
+                    continue;
+                }
+                *this.retries = 0;
+                if mapped < read_amount {
+                    // Keep the held bytes right after the served data
+                    this.buf
+                        .copy_within(read_amount..read_amount + *this.held, mapped);
+                }
             }
-            (this.process_fn).map_read(&mut this.buf[..read_amount]);
-            *this.cap = read_amount;
+            let mut rejected = None;
+            if *this.ascii_guard && !this.buf[base..mapped].is_ascii() {
+                rejected = Some(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "mapping function produced non-ASCII data",
+                ));
+            }
+            if let (None, Some(validator)) = (&rejected, this.validator.as_ref()) {
+                let mut validator = validator.lock().unwrap_or_else(PoisonError::into_inner);
+                rejected = validator(&this.buf[base..mapped]).err();
+            }
+            if let Some(e) = rejected {
+                // Drop the chunk, but keep the bytes held back for the next fill
+                this.buf.copy_within(mapped..mapped + *this.held, base);
+                return Poll::Ready(Err(e));
+            }
+            *this.cap = mapped;
+            if mapped == base {
+                // The mapping function dropped everything, read some more
+                continue;
+            }
+            *this.fills += 1;
+            return Poll::Ready(Ok(()));
         }
-        Poll::Ready(Ok(&this.buf[*this.pos..*this.cap]))
     }
+}
 
+impl<R: AsyncRead, F: TryMapReadFn> AsyncBufRead for AsyncMapReader<'_, R, F> {
+    #[inline]
+    fn poll_fill_buf(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        if self.limit == Some(0) {
+            if let Some(segmenter) = self.project().segmenter {
+                segmenter.finish();
+            }
+            return Poll::Ready(Ok(&[]));
+        }
+        if self.pos >= self.cap {
+            ready!(self.as_mut().poll_refill(cx))?;
+            if self.eof && self.pos >= self.cap {
+                let this = self.project();
+                if !*this.finalized {
+                    // Serve the footer from the mapping function ahead of the terminator
+                    let mut footer = Vec::new();
+                    (this.process_fn).finalize(&mut footer);
+                    *this.footer_len = footer.len();
+                    this.terminator.splice(..0, footer);
+                    *this.finalized = true;
+                }
+                let rem = &this.terminator[*this.terminator_pos..];
+                if let (Some(segmenter), []) = (this.segmenter, rem) {
+                    segmenter.finish();
+                }
+                return Poll::Ready(Ok(limited(rem, *this.limit)));
+            }
+        }
+        let this = self.project();
+        Poll::Ready(Ok(limited(&this.buf[*this.pos..*this.cap], *this.limit)))
+    }
+
+    #[inline]
     fn consume(self: Pin<&mut Self>, amt: usize) {
         let this = self.project();
-        *this.pos = std::cmp::min(*this.pos + amt, *this.cap);
+        let amt = limited_len(amt, *this.limit);
+        if *this.eof && *this.pos >= *this.cap {
+            let new_pos = std::cmp::min(*this.terminator_pos + amt, this.terminator.len());
+            if let Some(limit) = this.limit {
+                *limit -= (new_pos - *this.terminator_pos) as u64;
+            }
+            if let Some(segmenter) = this.segmenter {
+                segmenter.push(&this.terminator[*this.terminator_pos..new_pos]);
+            }
+            *this.terminator_pos = new_pos;
+            return;
+        }
+        let new_pos = std::cmp::min(*this.pos + amt, *this.cap);
+        if let Some(limit) = this.limit {
+            *limit -= (new_pos - *this.pos) as u64;
+        }
+        if let Some(stats) = this.stats {
+            stats.bytes_out += (new_pos - *this.pos) as u64;
+        }
+        if let Some((target, count)) = this.byte_counter {
+            let served = &this.buf[*this.pos..new_pos];
+            *count += served.iter().filter(|&b| b == target).count() as u64;
+        }
+        if let Some(segmenter) = this.segmenter {
+            segmenter.push(&this.buf[*this.pos..new_pos]);
+        }
+        *this.pos = new_pos;
+    }
+}
+
+/// Shortens `amt` to the number of bytes that may still be served, if limited.
+fn limited_len(amt: usize, limit: Option<u64>) -> usize {
+    match limit {
+        Some(limit) => std::cmp::min(amt as u64, limit) as usize,
+        None => amt,
+    }
+}
+
+/// Shortens `data` to the bytes that may still be served, if limited.
+fn limited(data: &[u8], limit: Option<u64>) -> &[u8] {
+    &data[..limited_len(data.len(), limit)]
+}
+
+impl<R: AsyncRead + AsyncSeek, F: TryMapReadFn> AsyncSeek for AsyncMapReader<'_, R, F> {
+    /// Seeks the underlying reader, discarding the buffered data.
+    /// If the seek fails, the buffered data is kept, and reading resumes where it stopped.
+    ///
+    /// For [`io::SeekFrom::Current`], the offset is relative to the mapped data served so far,
+    /// assuming the mapping function doesn't change the length of the data.
+    /// The mapping function isn't told about the seek, so it must not depend on data it has seen before,
+    /// but its `finalize` hook is called again at the next EOF. The limit set with [`AsyncMapReader::with_limit`]
+    /// and the segments of [`AsyncMapReader::with_segmenter`] start over as well.
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let mut this = self.project();
+        let pos = match pos {
+            io::SeekFrom::Current(n) => {
+                // The underlying reader is ahead by the data still buffered, mapped or not
+                let remainder = (*this.cap - *this.pos + *this.held) as i64;
+                let n = n.checked_sub(remainder).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "seek offset overflowed")
+                })?;
+                io::SeekFrom::Current(n)
+            }
+            pos => pos,
+        };
+        // Keep the buffered data if the seek fails, like `BufReader`
+        let res = ready!(this.inner.as_mut().poll_seek(cx, pos))?;
+        *this.pos = 0;
+        *this.cap = 0;
+        *this.held = 0;
+        *this.eof = false;
+        *this.terminator_pos = 0;
+        // Drop the footer, so it's produced again for the data read after the seek
+        this.terminator.drain(..*this.footer_len);
+        *this.footer_len = 0;
+        *this.finalized = false;
+        *this.deferred_error = None;
+        *this.retries = 0;
+        *this.limit = *this.limit_total;
+        if let Some(segmenter) = this.segmenter {
+            segmenter.reset();
+        }
+        *this.fills += 1;
+        Poll::Ready(Ok(res))
     }
 }
 
@@ -128,7 +1575,7 @@ impl<'a, R: AsyncRead> AsyncBufRead for AsyncMapReader<'a, R> {
 pub trait AsyncMapRead<'a, R> {
     /// Maps the underlying reader to an `AsyncMapReader` using the provided mapping function.
     /// This function uses a default buffer size (8KB) for the internal buffer.
-    fn map(self, f: impl MapReadFn + 'a) -> AsyncMapReader<'a, R>
+    fn map<F: MapReadFn + 'a>(self, f: F) -> AsyncMapReader<'a, R, F>
     where
         Self: Sized,
     {
@@ -140,11 +1587,45 @@ pub trait AsyncMapRead<'a, R> {
     ///
     /// This function allows for more control over the internal buffer size, which can be useful
     /// for performance tuning.
-    fn map_with_capacity(self, f: impl MapReadFn + 'a, capacity: usize) -> AsyncMapReader<'a, R>;
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    fn map_with_capacity<F: MapReadFn + 'a>(
+        self,
+        f: F,
+        capacity: usize,
+    ) -> AsyncMapReader<'a, R, F>;
+
+    /// Maps the underlying reader to an `AsyncMapReader` that never splits groups of `align` bytes
+    /// between calls to the mapping function, see [`AsyncMapReader::with_alignment`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is zero or larger than `capacity`.
+    fn map_aligned<F: MapReadFn + 'a>(
+        self,
+        f: F,
+        capacity: usize,
+        align: usize,
+    ) -> AsyncMapReader<'a, R, F>;
 }
 
 impl<'a, R: AsyncRead> AsyncMapRead<'a, R> for R {
-    fn map_with_capacity(self, f: impl MapReadFn + 'a, capacity: usize) -> AsyncMapReader<'a, R> {
-        AsyncMapReader::with_capacity(self, f, capacity)
+    fn map_with_capacity<F: MapReadFn + 'a>(
+        self,
+        f: F,
+        capacity: usize,
+    ) -> AsyncMapReader<'a, R, F> {
+        AsyncMapReader::from_fn_with_capacity(self, f, capacity)
+    }
+
+    fn map_aligned<F: MapReadFn + 'a>(
+        self,
+        f: F,
+        capacity: usize,
+        align: usize,
+    ) -> AsyncMapReader<'a, R, F> {
+        AsyncMapReader::aligned_groups(self, f, capacity, align)
     }
 }
@@ -0,0 +1,85 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, AsyncRead};
+
+use crate::{AsyncMapReader, MapReadFn, DEFAULT_BUFFER_SIZE};
+
+/// An in-memory `AsyncRead` that hands out its data in chunks of predetermined lengths.
+///
+/// After each chunk, the reader yields once (returning `Poll::Pending` and waking itself up),
+/// so an [`AsyncMapReader`] reading from it passes exactly those chunks to its mapping function.
+/// Once all lengths are used up, the remaining data is served without any particular boundaries.
+///
+/// This is usually constructed through [`AsyncMapReader::replay_chunks`], together with
+/// [`AsyncMapReader::record_chunks`] to reproduce chunk boundary dependent bugs deterministically.
+pub struct ChunkReplay {
+    data: Vec<u8>,
+    pos: usize,
+    lengths: VecDeque<usize>, // Remaining length of the current chunk, then the next ones
+    yield_next: bool,         // Whether the current chunk just ended
+}
+
+impl ChunkReplay {
+    /// Creates a new `ChunkReplay` serving `data` in chunks of `lengths` bytes.
+    pub fn new(data: Vec<u8>, lengths: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            data,
+            pos: 0,
+            lengths: lengths.into_iter().filter(|&len| len > 0).collect(),
+            yield_next: false,
+        }
+    }
+}
+
+impl AsyncRead for ChunkReplay {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.yield_next {
+            self.yield_next = false;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let remaining = self.data.len() - self.pos;
+        let chunk = self.lengths.front().copied().unwrap_or(remaining);
+        let amt = chunk.min(remaining).min(buf.len());
+        buf[..amt].copy_from_slice(&self.data[self.pos..self.pos + amt]);
+        self.pos += amt;
+        if let Some(len) = self.lengths.front_mut() {
+            *len -= amt;
+            if *len == 0 {
+                self.lengths.pop_front();
+                self.yield_next = true;
+            }
+        }
+        Poll::Ready(Ok(amt))
+    }
+}
+
+impl<'a> AsyncMapReader<'a, ChunkReplay> {
+    /// Create a new wrapper over in-memory `data`, passing chunks of exactly `lengths` bytes
+    /// to the mapping function.
+    ///
+    /// The lengths are usually obtained from [`AsyncMapReader::recorded_chunks`].
+    /// The buffer capacity is the largest of the lengths (or the default capacity if there are none),
+    /// so each chunk fits in the buffer.
+    pub fn replay_chunks(
+        data: Vec<u8>,
+        lengths: Vec<usize>,
+        process_fn: impl MapReadFn + 'a,
+    ) -> Self {
+        let capacity = lengths
+            .iter()
+            .copied()
+            .max()
+            .filter(|&len| len > 0)
+            .unwrap_or(DEFAULT_BUFFER_SIZE);
+        Self::with_capacity(ChunkReplay::new(data, lengths), process_fn, capacity)
+    }
+}
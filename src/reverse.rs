@@ -0,0 +1,108 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{
+    io::{self, AsyncSeek, SeekFrom},
+    ready, AsyncRead,
+};
+
+use crate::{AsyncMapReader, MapReadFn};
+
+pin_project_lite::pin_project! {
+  /// An `AsyncRead` serving the chunks of a seekable reader in reverse order.
+  ///
+  /// The reader is split into chunks of `chunk_size` bytes starting from its end,
+  /// so only the first chunk of the underlying reader may be shorter.
+  /// The chunks are served last to first, while the bytes within each chunk keep their order.
+  /// This is usually constructed through [`AsyncMapReader::reverse_chunks`].
+  pub struct ReverseChunks<R> {
+      #[pin]
+      inner: R,
+      chunk_size: usize,
+      len: Option<u64>, // Length of the underlying reader, once known
+      next_end: u64, // End of the next chunk to serve
+      pos: u64, // Position in the current chunk
+      end: u64, // End of the current chunk
+      seeking: bool, // Whether the underlying reader needs to seek to `pos`
+  }
+}
+
+impl<R: AsyncRead + AsyncSeek> ReverseChunks<R> {
+    /// Creates a new `ReverseChunks` serving `reader` in reverse order of `chunk_size` sized chunks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be at least 1");
+        Self {
+            inner: reader,
+            chunk_size,
+            len: None,
+            next_end: 0,
+            pos: 0,
+            end: 0,
+            seeking: false,
+        }
+    }
+
+    /// Consumes the `ReverseChunks` and returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek> AsyncRead for ReverseChunks<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        if this.len.is_none() {
+            let len = ready!(this.inner.as_mut().poll_seek(cx, SeekFrom::End(0)))?;
+            *this.len = Some(len);
+            *this.next_end = len;
+        }
+        if *this.pos == *this.end {
+            if *this.next_end == 0 {
+                return Poll::Ready(Ok(0));
+            }
+            let start = this.next_end.saturating_sub(*this.chunk_size as u64);
+            *this.pos = start;
+            *this.end = *this.next_end;
+            *this.next_end = start;
+            *this.seeking = true;
+        }
+        if *this.seeking {
+            ready!(this
+                .inner
+                .as_mut()
+                .poll_seek(cx, SeekFrom::Start(*this.pos)))?;
+            *this.seeking = false;
+        }
+        let amt = std::cmp::min(buf.len() as u64, *this.end - *this.pos) as usize;
+        let n = ready!(this.inner.as_mut().poll_read(cx, &mut buf[..amt]))?;
+        if n == 0 && amt > 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "reader ended before its reported length",
+            )));
+        }
+        *this.pos += n as u64;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<'a, R: AsyncRead + AsyncSeek> AsyncMapReader<'a, ReverseChunks<R>> {
+    /// Create a new wrapper mapping the chunks of `reader` from last to first.
+    ///
+    /// The reader is split into chunks of `capacity` bytes starting from its end (see [`ReverseChunks`]),
+    /// and each chunk is passed to the mapping function on its own, in reverse order.
+    /// The output is the concatenation of the mapped chunks, last chunk first.
+    pub fn reverse_chunks(reader: R, process_fn: impl MapReadFn + 'a, capacity: usize) -> Self {
+        Self::with_capacity(ReverseChunks::new(reader, capacity), process_fn, capacity)
+    }
+}
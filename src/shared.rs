@@ -0,0 +1,211 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures_lite::{io, ready, AsyncRead, AsyncWrite};
+
+use crate::{AsyncMapReader, AsyncMapWriter, MapReadFn, TryMapWriteFn};
+
+/// An `AsyncRead` reading from a reader shared behind an `Arc<Mutex<R>>`.
+///
+/// The lock is only held for the duration of each call to the underlying `poll_read`,
+/// and never across a `Poll::Pending`, so several readers can take turns reading from the same source.
+/// This is usually constructed through [`AsyncMapReader::from_shared`].
+pub struct SharedReader<R> {
+    inner: Arc<Mutex<R>>,
+}
+
+impl<R> SharedReader<R> {
+    /// Creates a new `SharedReader` reading from `inner`.
+    pub fn new(inner: Arc<Mutex<R>>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the shared underlying reader.
+    pub fn shared(&self) -> &Arc<Mutex<R>> {
+        &self.inner
+    }
+}
+
+impl<R> Clone for SharedReader<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for SharedReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|_| io::Error::other("shared reader mutex was poisoned"))?;
+        Pin::new(&mut *inner).poll_read(cx, buf)
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin> AsyncMapReader<'a, SharedReader<R>> {
+    /// Create a new wrapper reading from a source shared with other readers.
+    ///
+    /// The source is locked for each read from it, so each chunk passed to the mapping function
+    /// is made of data that no other reader sharing the source will see.
+    pub fn from_shared(reader: Arc<Mutex<R>>, process_fn: impl MapReadFn + 'a) -> Self {
+        Self::new(SharedReader::new(reader), process_fn)
+    }
+}
+
+/// A handle to an [`AsyncMapWriter`] shared by several tasks, writing whole messages that never interleave.
+///
+/// Cloning the handle shares the same writer. The handle is `Send` and `Sync` as long as the writer is `Send`,
+/// which takes a `Send` mapping function, e.g. with [`AsyncMapWriter::from_fn`] rather than the boxed default,
+/// so the tasks sharing it can run on a multi-threaded executor.
+pub struct SharedWriter<'a, W, F = Box<dyn TryMapWriteFn + 'a>> {
+    state: Arc<Mutex<SharedWriterState<'a, W, F>>>,
+}
+
+struct SharedWriterState<'a, W, F> {
+    writer: AsyncMapWriter<'a, W, F>,
+    busy: bool,          // Whether a message is being written
+    waiters: Vec<Waker>, // Tasks waiting for the current message to be written
+    remainder: Vec<u8>, // Rest of a message abandoned partway through, written ahead of the next one
+}
+
+/// Lets the next message through once the current one is written, failed, or abandoned.
+///
+/// An abandoned message is rolled back if none of it has been mapped yet,
+/// otherwise its remainder is kept so it's still written whole.
+struct MessageGuard<'s, 'm, 'a, W: AsyncWrite, F: TryMapWriteFn> {
+    state: &'s Mutex<SharedWriterState<'a, W, F>>,
+    message: &'m [u8],
+    written: usize,   // How much of the message the writer has accepted
+    owned: bool,      // Whether this message is the one being written
+    done: bool,       // Whether the message has been written and flushed
+    invocations: u64, // Calls to the mapping function when the message started
+    buffered: usize,  // Bytes in the writer's buffer when the message started
+}
+
+impl<W: AsyncWrite, F: TryMapWriteFn> Drop for MessageGuard<'_, '_, '_, W, F> {
+    fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // Nothing to undo if none of the message was accepted, or nothing to finish if all of it was
+        if !self.done && 0 < self.written && self.written < self.message.len() {
+            let writer = &mut state.writer;
+            let untouched = writer.transform_invocations() == self.invocations
+                && writer.buffered() == self.buffered + self.written;
+            if !(untouched && writer.rollback()) {
+                state
+                    .remainder
+                    .extend_from_slice(&self.message[self.written..]);
+            }
+        }
+        state.busy = false;
+        state.waiters.drain(..).for_each(Waker::wake);
+    }
+}
+
+impl<'a, W: AsyncWrite + Unpin, F: TryMapWriteFn> SharedWriter<'a, W, F> {
+    /// Creates a new `SharedWriter` around `writer`.
+    pub fn new(writer: AsyncMapWriter<'a, W, F>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SharedWriterState {
+                writer,
+                busy: false,
+                waiters: Vec::new(),
+                remainder: Vec::new(),
+            })),
+        }
+    }
+
+    /// Writes all of `message` and flushes it, without letting any other message through in between.
+    ///
+    /// Other tasks calling this wait for the current message to be written.
+    /// The lock on the writer is only held within each poll, never across a `Poll::Pending`.
+    ///
+    /// If the returned future is dropped partway through, the message is discarded if none of it
+    /// has been passed to the mapping function yet. Otherwise part of it may already have been written out,
+    /// so the rest is written ahead of the next message, and messages are still never cut short or interleaved.
+    pub async fn write_message(&self, message: &[u8]) -> io::Result<()> {
+        let mut guard = MessageGuard {
+            state: &self.state,
+            message,
+            written: 0,
+            owned: false,
+            done: false,
+            invocations: 0,
+            buffered: 0,
+        };
+        let res = futures_lite::future::poll_fn(|cx| {
+            let mut state = guard
+                .state
+                .lock()
+                .map_err(|_| io::Error::other("shared writer mutex was poisoned"))?;
+            let state = &mut *state;
+            if !guard.owned {
+                if state.busy {
+                    // A task polled again before being woken is only registered once
+                    if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                        state.waiters.push(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                }
+                state.busy = true;
+                guard.owned = true;
+            }
+            // Finish a message abandoned partway through first
+            while !state.remainder.is_empty() {
+                let n = ready!(Pin::new(&mut state.writer).poll_write(cx, &state.remainder))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                state.remainder.drain(..n);
+            }
+            if guard.written == 0 {
+                guard.invocations = state.writer.transform_invocations();
+                guard.buffered = state.writer.buffered();
+                state.writer.begin();
+            }
+            while guard.written < message.len() {
+                let rest = &message[guard.written..];
+                let n = ready!(Pin::new(&mut state.writer).poll_write(cx, rest))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                guard.written += n;
+            }
+            Pin::new(&mut state.writer).poll_flush(cx)
+        })
+        .await;
+        guard.done = res.is_ok();
+        res
+    }
+
+    /// Returns the shared writer, if this is the last handle to it.
+    pub fn into_inner(self) -> Option<AsyncMapWriter<'a, W, F>> {
+        let state = Arc::into_inner(self.state)?;
+        let state = state
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Some(state.writer)
+    }
+}
+
+impl<W, F> Clone for SharedWriter<'_, W, F> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{
+    io::{self, Cursor},
+    AsyncWrite,
+};
+
+use crate::MapReadFn;
+
+/// An in-memory destination that written data can be appended to directly.
+///
+/// This lets [`InMemoryMapWriter`] map data in place at the end of the destination,
+/// instead of mapping it in a buffer of its own and copying it over.
+pub trait InMemorySink {
+    /// Returns the vector written data is appended to.
+    fn vec_mut(&mut self) -> &mut Vec<u8>;
+
+    /// Called after `len` bytes were appended to the vector, and mapped.
+    fn appended(&mut self, len: usize) {
+        let _ = len;
+    }
+}
+
+impl InMemorySink for Vec<u8> {
+    fn vec_mut(&mut self) -> &mut Vec<u8> {
+        self
+    }
+}
+
+/// Data is always appended at the end of the vector, whatever the position of the cursor,
+/// and the position is then moved to the end.
+impl InMemorySink for Cursor<Vec<u8>> {
+    fn vec_mut(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn appended(&mut self, _len: usize) {
+        let end = self.get_ref().len() as u64;
+        self.set_position(end);
+    }
+}
+
+/// An `AsyncWrite` mapping data directly at the end of an in-memory destination.
+///
+/// Unlike an [`AsyncMapWriter`](crate::AsyncMapWriter) around the same destination,
+/// the data is copied once, into the destination, and the mapping function works in place there,
+/// so it must keep the length of the data, like for an [`AsyncMapReader`](crate::AsyncMapReader).
+/// Each write is mapped as one chunk, and nothing is buffered, so flushing does nothing.
+pub struct InMemoryMapWriter<'a, S> {
+    sink: S,
+    process_fn: Box<dyn MapReadFn + 'a>,
+}
+
+impl<'a, S: InMemorySink> InMemoryMapWriter<'a, S> {
+    /// Creates a new `InMemoryMapWriter` appending the data written to `sink`, mapped with `process_fn`.
+    pub fn new(sink: S, process_fn: impl MapReadFn + 'a) -> Self {
+        Self {
+            sink,
+            process_fn: Box::new(process_fn),
+        }
+    }
+
+    /// Gets a reference to the destination.
+    pub fn get_ref(&self) -> &S {
+        &self.sink
+    }
+
+    /// Consumes the writer and returns the destination.
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+impl<S: InMemorySink + Unpin> AsyncWrite for InMemoryMapWriter<'_, S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let vec = this.sink.vec_mut();
+        let start = vec.len();
+        vec.extend_from_slice(buf);
+        this.process_fn.map_read(&mut vec[start..]);
+        this.sink.appended(buf.len());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
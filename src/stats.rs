@@ -0,0 +1,30 @@
+/// A summary of the work done by an [`AsyncMapReader`](crate::AsyncMapReader)
+/// or an [`AsyncMapWriter`](crate::AsyncMapWriter).
+///
+/// Statistics are only collected once enabled with `with_stats`, to avoid any overhead when unused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapStats {
+    /// Number of bytes going into the wrapper.
+    ///
+    /// For a reader, these are the bytes read from the underlying reader.
+    /// For a writer, these are the bytes accepted from the caller.
+    pub bytes_in: u64,
+    /// Number of bytes coming out of the wrapper.
+    ///
+    /// For a reader, these are the bytes served to the caller.
+    /// For a writer, these are the bytes written to the underlying writer.
+    pub bytes_out: u64,
+    /// Number of times the mapping function has been called.
+    pub transform_invocations: u64,
+    /// Number of successful reads from, or writes to, the underlying reader or writer.
+    pub inner_calls: u64,
+    /// Length of the largest chunk passed to the mapping function.
+    pub max_chunk: usize,
+}
+
+impl MapStats {
+    /// Records a chunk of `len` bytes being passed to the mapping function.
+    pub(crate) fn record_chunk(&mut self, len: usize) {
+        self.max_chunk = self.max_chunk.max(len);
+    }
+}
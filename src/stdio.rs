@@ -0,0 +1,34 @@
+use std::io::{Read, Stdin, Stdout, Write};
+
+use crate::{AsyncMapReader, AsyncMapWriter, MapReadFn, MapWriteFn};
+use blocking::Unblock;
+
+impl<'a, R: Read + Send + 'static> AsyncMapReader<'a, Unblock<R>> {
+    /// Create a new wrapper around a blocking reader, which is read on a thread pool.
+    pub fn from_blocking(reader: R, process_fn: impl MapReadFn + 'a) -> Self {
+        Self::new(Unblock::new(reader), process_fn)
+    }
+}
+
+impl<'a> AsyncMapReader<'a, Unblock<Stdin>> {
+    /// Create a new wrapper mapping the data read from the standard input.
+    pub fn stdin(process_fn: impl MapReadFn + 'a) -> Self {
+        Self::from_blocking(std::io::stdin(), process_fn)
+    }
+}
+
+impl<'a, W: Write + Send + 'static> AsyncMapWriter<'a, Unblock<W>> {
+    /// Creates a new `AsyncMapWriter` around a blocking writer, which is written to on a thread pool.
+    pub fn from_blocking(writer: W, process_fn: impl MapWriteFn + 'a) -> Self {
+        Self::new(Unblock::new(writer), process_fn)
+    }
+}
+
+impl<'a> AsyncMapWriter<'a, Unblock<Stdout>> {
+    /// Creates a new `AsyncMapWriter` mapping the data written to the standard output.
+    ///
+    /// Remember to flush (or close) the writer before exiting, or the buffered data is lost.
+    pub fn stdout(process_fn: impl MapWriteFn + 'a) -> Self {
+        Self::from_blocking(std::io::stdout(), process_fn)
+    }
+}
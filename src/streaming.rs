@@ -0,0 +1,156 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{io, ready, AsyncWrite};
+
+use crate::DEFAULT_BUFFER_SIZE;
+
+/// The default size of the output window of a [`StreamingMapWriter`].
+const DEFAULT_WINDOW_SIZE: usize = 1024;
+
+/// A trait for mapping data written to an underlying writer, producing the output incrementally.
+pub trait StreamingMapWriteFn {
+    /// Maps `input`, appending the mapped data to `sink`.
+    ///
+    /// `sink` may still hold output from previous calls that hasn't been written out yet,
+    /// so it must only be appended to.
+    fn map_write_streaming(&mut self, input: &[u8], sink: &mut Vec<u8>);
+}
+
+impl<F> StreamingMapWriteFn for F
+where
+    F: FnMut(&[u8], &mut Vec<u8>),
+{
+    fn map_write_streaming(&mut self, input: &[u8], sink: &mut Vec<u8>) {
+        self(input, sink)
+    }
+}
+
+pin_project_lite::pin_project! {
+  /// A wrapper around an `AsyncWrite` that maps data through a [`StreamingMapWriteFn`].
+  ///
+  /// Like [`AsyncMapWriter`](crate::AsyncMapWriter), data is buffered up to the specified capacity.
+  /// When flushing, the buffered data is fed to the mapping function in pieces of at most `window` bytes,
+  /// and the output is written to the underlying writer whenever it reaches `window` bytes.
+  /// This keeps memory usage bounded for mapping functions that greatly expand the data,
+  /// as the expanded output of a whole chunk is never held at once.
+  pub struct StreamingMapWriter<'a, W> {
+     #[pin]
+     inner: W,
+     process_fn: Box<dyn StreamingMapWriteFn + 'a>,
+     input: Vec<u8>, // Data written but not yet flushed
+     capacity: usize, // Maximum amount of buffered input
+     consumed: usize, // How much of `input` has been passed to the mapping function
+     output: Vec<u8>, // Mapped data not yet written
+     written: usize, // How much of `output` has been written
+     window: usize, // Size of the output window
+  }
+}
+
+impl<'a, W: AsyncWrite> StreamingMapWriter<'a, W> {
+    /// Creates a new `StreamingMapWriter` with a default buffer size of 8KB and a 1KB output window.
+    pub fn new(writer: W, process_fn: impl StreamingMapWriteFn + 'a) -> Self {
+        Self::with_window(writer, process_fn, DEFAULT_BUFFER_SIZE, DEFAULT_WINDOW_SIZE)
+    }
+
+    /// Creates a new `StreamingMapWriter` with a specified buffer capacity and output window size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` or `window` is zero.
+    pub fn with_window(
+        writer: W,
+        process_fn: impl StreamingMapWriteFn + 'a,
+        capacity: usize,
+        window: usize,
+    ) -> Self {
+        assert!(capacity > 0, "capacity must be at least 1");
+        assert!(window > 0, "window size must be at least 1");
+        Self {
+            inner: writer,
+            process_fn: Box::new(process_fn),
+            input: Vec::with_capacity(capacity),
+            capacity,
+            consumed: 0,
+            output: Vec::with_capacity(window),
+            written: 0,
+            window,
+        }
+    }
+
+    /// Consumes the `StreamingMapWriter` and returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Maps the buffered input and writes it to the underlying writer, one window at a time.
+    fn poll_flush_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        loop {
+            // Write out the output window once it's full, or once all the input has been mapped
+            let input_done = *this.consumed == this.input.len();
+            if *this.written < this.output.len()
+                && (this.output.len() >= *this.window || input_done)
+            {
+                while *this.written < this.output.len() {
+                    match this
+                        .inner
+                        .as_mut()
+                        .poll_write(cx, &this.output[*this.written..])
+                    {
+                        Poll::Ready(Ok(0)) => {
+                            return Poll::Ready(Err(io::Error::new(
+                                io::ErrorKind::WriteZero,
+                                "write zero",
+                            )));
+                        }
+                        Poll::Ready(Ok(n)) => *this.written += n,
+                        Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => {}
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                this.output.clear();
+                *this.written = 0;
+                continue;
+            }
+            if input_done {
+                break;
+            }
+            let end = std::cmp::min(*this.consumed + *this.window, this.input.len());
+            (this.process_fn).map_write_streaming(&this.input[*this.consumed..end], this.output);
+            *this.consumed = end;
+        }
+        this.input.clear();
+        *this.consumed = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for StreamingMapWriter<'_, W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.input.is_empty() && self.input.len() + buf.len() > self.capacity {
+            ready!(self.as_mut().poll_flush_buf(cx))?;
+        }
+        let this = self.project();
+        let amt = std::cmp::min(buf.len(), *this.capacity - this.input.len());
+        this.input.extend_from_slice(&buf[..amt]);
+        Poll::Ready(Ok(amt))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.project().inner.poll_close(cx)
+    }
+}
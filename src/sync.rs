@@ -0,0 +1,41 @@
+use std::{io, pin::Pin};
+
+use futures_lite::{future::block_on, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt};
+
+use crate::{AsyncMapReader, TryMapReadFn};
+
+/// A blocking `std::io::BufRead` over an [`AsyncMapReader`], for synchronous consumers.
+///
+/// Every call drives the reader to completion on the current thread with a local executor,
+/// so this must not be used from within an async context.
+pub struct BlockingBufRead<'a, R, F = Box<dyn TryMapReadFn + 'a>> {
+    inner: AsyncMapReader<'a, R, F>,
+}
+
+impl<'a, R: AsyncRead + Unpin, F: TryMapReadFn> BlockingBufRead<'a, R, F> {
+    /// Creates a new `BlockingBufRead` reading from `inner`.
+    pub fn new(inner: AsyncMapReader<'a, R, F>) -> Self {
+        Self { inner }
+    }
+
+    /// Consumes the `BlockingBufRead` and returns the wrapped reader.
+    pub fn into_inner(self) -> AsyncMapReader<'a, R, F> {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin, F: TryMapReadFn> io::Read for BlockingBufRead<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        block_on(self.inner.read(buf))
+    }
+}
+
+impl<R: AsyncRead + Unpin, F: TryMapReadFn> io::BufRead for BlockingBufRead<'_, R, F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        block_on(self.inner.fill_buf())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        Pin::new(&mut self.inner).consume(amt)
+    }
+}
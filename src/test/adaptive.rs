@@ -0,0 +1,50 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+
+use crate::{AdaptiveMap, AsyncMapReader, ChunkReplay};
+
+/// Counts the bytes seen so far, and replaces each byte with its rank among them (0 = most frequent).
+fn rank(counts: &mut [u64; 256], buf: &mut [u8]) {
+    for b in buf {
+        counts[*b as usize] += 1;
+        let count = counts[*b as usize];
+        *b = counts.iter().filter(|&&c| c > count).count() as u8;
+    }
+}
+
+#[test]
+fn recover_state() {
+    let adaptive = AdaptiveMap::new([0u64; 256], rank);
+    let source = ChunkReplay::new(b"abacab".to_vec(), [2, 2, 2]);
+    let mut out = Vec::new();
+    let mut reader = AsyncMapReader::from_fn(source, adaptive);
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, [0, 0, 0, 1, 0, 1]);
+    // The state carried over between chunks, and can be inspected once the reader is done
+    let counts = reader.into_process_fn().into_state();
+    assert_eq!(
+        (
+            counts[b'a' as usize],
+            counts[b'b' as usize],
+            counts[b'c' as usize]
+        ),
+        (3, 2, 1)
+    );
+}
+
+#[test]
+fn capturing_closure() {
+    // Adds a key, captured by the closure, to a running sum kept in the state
+    let key = 3u8;
+    let adaptive = AdaptiveMap::new(0u8, move |sum: &mut u8, buf: &mut [u8]| {
+        for b in buf {
+            *sum = sum.wrapping_add(*b);
+            *b = sum.wrapping_add(key);
+        }
+    });
+    let source = ChunkReplay::new(vec![1, 2, 3, 4], [1, 3]);
+    let mut reader = AsyncMapReader::from_fn(source, adaptive);
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, [4, 6, 9, 13]);
+    assert_eq!(*reader.into_process_fn().state(), 10);
+}
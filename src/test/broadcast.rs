@@ -0,0 +1,82 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{future::block_on, AsyncWrite, AsyncWriteExt};
+
+use crate::{AsyncMapWriter, BroadcastPolicy};
+
+#[test]
+fn broadcast_to_two_sinks() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::broadcast(vec![Vec::new(), Vec::new()], transformer);
+    block_on(async {
+        writer.write_all(b"hello ").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"world").await.unwrap();
+        writer.close().await.unwrap();
+    });
+
+    let sinks = writer.into_inner().into_inner();
+    assert_eq!(sinks.len(), 2);
+    for sink in sinks {
+        assert_eq!(sink, b"HELLO WORLD");
+    }
+}
+
+// A writer that always fails
+struct Broken;
+
+impl AsyncWrite for Broken {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken")))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn sinks() -> Vec<Box<dyn AsyncWrite + Unpin>> {
+    vec![Box::new(Vec::new()), Box::new(Broken), Box::new(Vec::new())]
+}
+
+#[test]
+fn broadcast_fail_fast() {
+    let mut writer = AsyncMapWriter::broadcast(sinks(), |_: &mut Vec<u8>| {});
+    block_on(async {
+        writer.write_all(b"data").await.unwrap();
+        let err = writer.flush().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    });
+}
+
+#[test]
+fn broadcast_best_effort() {
+    let mut writer = AsyncMapWriter::broadcast_with_policy(
+        sinks(),
+        BroadcastPolicy::BestEffort,
+        |_: &mut Vec<u8>| {},
+    );
+    block_on(async {
+        writer.write_all(b"data").await.unwrap();
+        writer.close().await.unwrap();
+    });
+
+    let broadcast = writer.into_inner();
+    let errors = broadcast
+        .errors()
+        .map(|(i, e)| (i, e.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(errors, [(1, io::ErrorKind::BrokenPipe)]);
+}
@@ -0,0 +1,96 @@
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt, AsyncWriteExt};
+
+use crate::{AsyncMapReader, AsyncMapWriter, Cipher, DecryptRead, EncryptWrite};
+
+/// XORs every byte with a keystream depending on its position.
+struct Counter {
+    key: u8,
+}
+
+impl Counter {
+    fn new(key: u8) -> Self {
+        Self { key }
+    }
+
+    fn apply(&self, buf: &mut [u8], offset: u64) {
+        for (position, b) in (offset..).zip(buf) {
+            *b ^= self.key.wrapping_add(position as u8).rotate_left(3);
+        }
+    }
+}
+
+impl Cipher for Counter {
+    fn encrypt(&mut self, buf: &mut [u8], offset: u64) {
+        self.apply(buf, offset);
+    }
+
+    fn decrypt(&mut self, buf: &mut [u8], offset: u64) {
+        self.apply(buf, offset);
+    }
+}
+
+#[test]
+fn cipher_round_trip() {
+    let data = (0..100).map(|i| (i * 7) as u8).collect::<Vec<u8>>();
+
+    let cipher = EncryptWrite::new(Counter::new(42));
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), cipher, 7);
+    block_on(async {
+        // Writes that don't line up with the chunks
+        for piece in data.chunks(11) {
+            writer.write_all(piece).await.unwrap();
+        }
+        writer.close().await.unwrap();
+    });
+    let encrypted = writer.into_inner().into_inner();
+    assert_eq!(encrypted.len(), data.len());
+    assert_ne!(encrypted, data);
+
+    let cipher = DecryptRead::new(Counter::new(42));
+    let mut reader = AsyncMapReader::with_capacity(Cursor::new(encrypted), cipher, 5);
+    let mut decrypted = Vec::new();
+    block_on(reader.read_to_end(&mut decrypted)).unwrap();
+    assert_eq!(decrypted, data);
+}
+
+#[test]
+fn cipher_constructors() {
+    let mut writer = AsyncMapWriter::encrypt(Cursor::new(vec![]), Counter::new(7));
+    block_on(async {
+        writer.write_all(b"secret message").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    let encrypted = writer.into_inner().into_inner();
+
+    let mut reader = AsyncMapReader::decrypt(Cursor::new(encrypted), Counter::new(7));
+    let mut decrypted = Vec::new();
+    block_on(reader.read_to_end(&mut decrypted)).unwrap();
+    assert_eq!(decrypted, b"secret message");
+}
+
+#[test]
+fn cipher_offset() {
+    let mut cipher = EncryptWrite::new(Counter::new(1));
+    crate::MapWriteFn::map_write(&mut cipher, &mut vec![0; 10]);
+    assert_eq!(cipher.offset(), 10);
+}
+
+#[test]
+fn cipher_from_offset() {
+    let data = (0..50).collect::<Vec<u8>>();
+    let cipher = EncryptWrite::new(Counter::new(9));
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), cipher, 8);
+    block_on(async {
+        writer.write_all(&data).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    let encrypted = writer.into_inner().into_inner();
+
+    // Decrypt only the tail of the stream, as after seeking to it
+    let cipher = DecryptRead::with_offset(Counter::new(9), 20);
+    let mut reader =
+        AsyncMapReader::with_capacity(Cursor::new(encrypted[20..].to_vec()), cipher, 6);
+    let mut decrypted = Vec::new();
+    block_on(reader.read_to_end(&mut decrypted)).unwrap();
+    assert_eq!(decrypted, &data[20..]);
+}
@@ -0,0 +1,24 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{AsyncMapReader, AsyncMapWriter};
+
+#[tokio::test]
+async fn tokio_duplex() {
+    let (client, server) = tokio::io::duplex(16);
+    let mut writer =
+        AsyncMapWriter::from_tokio(client, |buf: &mut Vec<u8>| buf.make_ascii_uppercase());
+    let mut reader = AsyncMapReader::from_tokio(server, |buf: &mut [u8]| buf.reverse());
+
+    let write = async {
+        writer.write_all(b"abc").await.unwrap();
+        // Shutting down the writer flushes it and closes the duplex
+        writer.shutdown().await.unwrap();
+    };
+    let read = async {
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        out
+    };
+    let ((), out) = tokio::join!(write, read);
+    assert_eq!(out, b"CBA");
+}
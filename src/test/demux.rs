@@ -0,0 +1,57 @@
+use std::sync::mpsc;
+
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+
+use crate::{AsyncMapReader, ChunkReplay};
+
+/// Sends the bytes at odd positions of the stream to the sideband.
+fn split_odd(offset: &mut usize, chunk: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut main = Vec::new();
+    let mut sideband = Vec::new();
+    for &b in chunk {
+        if offset.is_multiple_of(2) {
+            main.push(b);
+        } else {
+            sideband.push(b);
+        }
+        *offset += 1;
+    }
+    (main, sideband)
+}
+
+#[test]
+fn demux_odd_bytes() {
+    let (tx, rx) = mpsc::channel();
+    let mut offset = 0;
+    let data = Cursor::new(b"aAbBcCdDe".to_vec());
+    let mut reader = AsyncMapReader::with_demux(
+        data,
+        move |chunk| split_odd(&mut offset, chunk),
+        move |bytes| tx.send(bytes).unwrap(),
+    );
+    let mut main = Vec::new();
+    block_on(reader.read_to_end(&mut main)).unwrap();
+    drop(reader);
+
+    assert_eq!(main, b"abcde");
+    assert_eq!(rx.iter().flatten().collect::<Vec<u8>>(), b"ABCD");
+}
+
+#[test]
+fn demux_across_chunks() {
+    let mut sideband = Vec::new();
+    let mut offset = 0;
+    // A chunk of a single odd byte produces no main stream data
+    let data = ChunkReplay::new(b"aAbBcC".to_vec(), [1, 1, 3, 1]);
+    let mut reader = AsyncMapReader::with_demux(
+        data,
+        |chunk| split_odd(&mut offset, chunk),
+        |bytes| sideband.extend(bytes),
+    );
+    let mut main = Vec::new();
+    block_on(reader.read_to_end(&mut main)).unwrap();
+    drop(reader);
+
+    assert_eq!(main, b"abc");
+    assert_eq!(sideband, b"ABC");
+}
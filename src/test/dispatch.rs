@@ -0,0 +1,50 @@
+use std::io;
+
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+
+use crate::{AsyncMapReader, ChunkReplay, MapReadFn};
+
+fn registry() -> Vec<Box<dyn MapReadFn>> {
+    vec![
+        Box::new(|_: &mut [u8]| {}),
+        Box::new(|buf: &mut [u8]| buf.reverse()),
+    ]
+}
+
+#[test]
+fn dispatch_by_header() {
+    // Reads that don't line up with the chunks
+    let data = b"\x00abc\x01def\x00ghi\x01jk".to_vec();
+    let source = ChunkReplay::new(data, [3, 6, 1, 5]);
+    let mut reader =
+        AsyncMapReader::with_dispatch(source, 4, 1, |header| header[0] as usize, registry());
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"abcfedghikj");
+}
+
+#[test]
+fn dispatch_over_cursor() {
+    // The whole stream comes in a single read
+    let data = b"\x01abc\x00def\x01ghi\x01j".to_vec();
+    let mut reader = AsyncMapReader::with_dispatch(
+        Cursor::new(data),
+        4,
+        1,
+        |header| header[0] as usize,
+        registry(),
+    );
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"cbadefihgj");
+}
+
+#[test]
+fn dispatch_unknown_transform() {
+    let source = ChunkReplay::new(b"\x02abc".to_vec(), [4]);
+    let mut reader =
+        AsyncMapReader::with_dispatch(source, 4, 1, |header| header[0] as usize, registry());
+    let mut result = Vec::new();
+    let err = block_on(reader.read_to_end(&mut result)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
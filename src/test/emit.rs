@@ -0,0 +1,57 @@
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt, AsyncWriteExt};
+
+use crate::{AsyncMapWriter, AsyncVecMapReader, ChunkReplay};
+
+// Emits every byte twice, one byte at a time
+fn double(input: &[u8], emit: &mut dyn FnMut(&[u8])) {
+    for &b in input {
+        emit(&[b]);
+        emit(&[b]);
+    }
+}
+
+#[test]
+fn emitting_reader() {
+    let source = ChunkReplay::new(b"abcdefg".to_vec(), [3, 1, 3]);
+    let mut reader = AsyncVecMapReader::with_emit(source, double);
+    let mut out = Vec::new();
+    let mut buf = [0; 5];
+    loop {
+        let n = block_on(reader.read(&mut buf)).unwrap();
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(out, b"aabbccddeeffgg");
+}
+
+#[test]
+fn emitting_writer() {
+    let mut writer = AsyncMapWriter::with_emit(Cursor::new(vec![]), double);
+    block_on(async {
+        writer.write_all(b"abc").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"de").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"aabbccddee");
+}
+
+#[test]
+fn emitting_writer_dropping_a_chunk() {
+    // Emits nothing for chunks starting with `x`
+    let filter = |input: &[u8], emit: &mut dyn FnMut(&[u8])| {
+        if input.first() != Some(&b'x') {
+            emit(input);
+        }
+    };
+    let mut writer = AsyncMapWriter::with_emit(Cursor::new(vec![]), filter);
+    block_on(async {
+        writer.write_all(b"xdrop").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"keep").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"keep");
+}
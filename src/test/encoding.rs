@@ -0,0 +1,71 @@
+use std::io;
+
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt, AsyncWriteExt};
+
+use crate::{AsyncMapWriter, ChunkReplay, Utf16ToUtf8Read, Utf8ToUtf16Write};
+
+const TEXT: &str = "héllo wörld, ナイス 🦀🦀!";
+
+#[test]
+fn utf8_to_utf16_across_chunks() {
+    let expected = TEXT
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<u8>>();
+    // Small chunks split multibyte characters
+    for capacity in 1..8 {
+        let mut writer =
+            AsyncMapWriter::with_capacity(Cursor::new(vec![]), Utf8ToUtf16Write::new(), capacity);
+        block_on(async {
+            writer.write_all(TEXT.as_bytes()).await.unwrap();
+            writer.close().await.unwrap();
+        });
+        assert_eq!(writer.into_inner().into_inner(), expected);
+    }
+}
+
+#[test]
+fn utf8_to_utf16_invalid() {
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), Utf8ToUtf16Write::new());
+    block_on(async {
+        // An invalid byte, then a character cut short by the end of the stream
+        writer.write_all(b"a\xFFb\xE3\x83").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    let expected = "a\u{FFFD}b\u{FFFD}"
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<u8>>();
+    assert_eq!(writer.into_inner().into_inner(), expected);
+}
+
+#[test]
+fn round_trip() {
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), Utf8ToUtf16Write::new(), 5);
+    block_on(async {
+        writer.write_all(TEXT.as_bytes()).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    let utf16 = writer.into_inner().into_inner();
+
+    // Odd chunk lengths split code units and surrogate pairs
+    let lengths = vec![3; utf16.len() / 3];
+    let mut reader = Utf16ToUtf8Read::new(ChunkReplay::new(utf16, lengths));
+    let mut result = String::new();
+    block_on(reader.read_to_string(&mut result)).unwrap();
+    assert_eq!(result, TEXT);
+}
+
+#[test]
+fn utf16_to_utf8_invalid() {
+    // A lone high surrogate at EOF
+    let mut reader = Utf16ToUtf8Read::new(Cursor::new(vec![b'a', 0, 0x3D, 0xD8]));
+    let mut result = Vec::new();
+    let err = block_on(reader.read_to_end(&mut result)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // An odd number of bytes
+    let mut reader = Utf16ToUtf8Read::new(Cursor::new(vec![b'a', 0, b'b']));
+    let err = block_on(reader.read_to_end(&mut result)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,69 @@
+use futures_lite::future::block_on;
+
+use crate::transform_file;
+
+#[test]
+fn transform_file_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.txt");
+    std::fs::write(&path, b"hello file").unwrap();
+
+    block_on(transform_file(&path, |buf: &mut [u8]| {
+        buf.make_ascii_uppercase()
+    }))
+    .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"HELLO FILE");
+    // No temporary file is left behind
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn transform_missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.txt");
+    let err = block_on(transform_file(&path, |_: &mut [u8]| {})).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn transform_file_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("script.sh");
+    std::fs::write(&path, b"echo hi\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o750)).unwrap();
+
+    block_on(transform_file(&path, |buf: &mut [u8]| {
+        buf.make_ascii_uppercase()
+    }))
+    .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"ECHO HI\n");
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+}
+
+#[test]
+fn transform_file_concurrently() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.txt");
+    std::fs::write(&path, b"abc").unwrap();
+    // A file with the name the temporary file used to have is left alone
+    let bystander = dir.path().join(".data.txt.tmp");
+    std::fs::write(&bystander, b"keep").unwrap();
+
+    let increment = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 1);
+    let (a, b) = block_on(futures_lite::future::zip(
+        transform_file(&path, increment),
+        transform_file(&path, increment),
+    ));
+    a.unwrap();
+    b.unwrap();
+    // Either transform may have read the original, but each wrote a whole file
+    let data = std::fs::read(&path).unwrap();
+    assert!(data == b"bcd" || data == b"cde", "{data:?}");
+    assert_eq!(std::fs::read(&bystander).unwrap(), b"keep");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
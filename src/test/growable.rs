@@ -0,0 +1,79 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+
+use crate::{AsyncVecMapReader, ChunkReplay};
+
+#[test]
+fn growing_mapping() {
+    let chunks = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let seen = std::rc::Rc::clone(&chunks);
+    // Doubles every byte
+    let double = move |buf: &mut Vec<u8>| {
+        seen.borrow_mut().push(buf.len());
+        *buf = buf.iter().flat_map(|&b| [b, b]).collect();
+    };
+    let mut reader = AsyncVecMapReader::with_capacity(&b"abcdefg"[..], double, 3);
+    // Smaller reads than the mapped chunks
+    let mut out = Vec::new();
+    let mut buf = [0; 4];
+    loop {
+        let n = block_on(reader.read(&mut buf)).unwrap();
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(out, b"aabbccddeeffgg");
+    // The chunks passed to the mapping function never exceed the capacity
+    assert_eq!(*chunks.borrow(), [3, 3, 1]);
+}
+
+#[test]
+fn shrinking_mapping() {
+    // Decodes pairs of hex digits, assuming chunks of whole pairs
+    let decode = |buf: &mut Vec<u8>| {
+        let hex = std::str::from_utf8(buf).unwrap();
+        *buf = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+    };
+    let source = ChunkReplay::new(b"68690a".to_vec(), [2, 4]);
+    let mut reader = AsyncVecMapReader::new(source, decode);
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, b"hi\n");
+}
+
+#[test]
+fn raw_range_of_buffered_data() {
+    use futures_lite::AsyncBufReadExt;
+
+    let double = |buf: &mut Vec<u8>| *buf = buf.iter().flat_map(|&b| [b, b]).collect();
+    let mut reader = AsyncVecMapReader::with_capacity(&b"abcdefg"[..], double, 3);
+    assert_eq!(reader.current_raw_range(), 0..0);
+    let mut mapped_start = 0;
+    let mut ranges = Vec::new();
+    block_on(async {
+        loop {
+            let len = reader.fill_buf().await.unwrap().len();
+            if len == 0 {
+                break;
+            }
+            let raw = reader.current_raw_range();
+            // The mapped data is twice as long as the raw data it came from
+            assert_eq!(mapped_start, raw.start * 2);
+            assert_eq!(len as u64, (raw.end - raw.start) * 2);
+            ranges.push(raw);
+            mapped_start += len as u64;
+            reader.consume(len);
+        }
+    });
+    assert_eq!(ranges, [0..3, 3..6, 6..7]);
+    assert_eq!(reader.current_raw_range(), 7..7);
+}
+
+#[test]
+#[should_panic(expected = "capacity must be at least 1")]
+fn zero_capacity() {
+    let _ = AsyncVecMapReader::with_capacity(&b"abc"[..], |_: &mut Vec<u8>| {}, 0);
+}
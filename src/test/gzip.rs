@@ -0,0 +1,41 @@
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use futures_lite::{future::block_on, io::Cursor, AsyncWriteExt};
+
+use crate::{AsyncMapWriter, GzipEncodeWrite};
+
+#[test]
+fn gzip_round_trip() {
+    let data = (0..50_000)
+        .map(|i| (i % 251) as u8)
+        .chain(b"some trailing text".iter().copied())
+        .collect::<Vec<u8>>();
+    let mut writer =
+        AsyncMapWriter::with_capacity(Cursor::new(vec![]), GzipEncodeWrite::new(), 1024);
+    block_on(async {
+        writer.write_all(&data).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    let compressed = writer.into_inner().into_inner();
+    assert!(compressed.len() < data.len());
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, data);
+}
+
+#[test]
+fn gzip_empty() {
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), GzipEncodeWrite::new());
+    block_on(writer.close()).unwrap();
+    let compressed = writer.into_inner().into_inner();
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert!(decompressed.is_empty());
+}
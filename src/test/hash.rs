@@ -0,0 +1,73 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+
+use crate::{AsyncMapRead, AsyncMapReader, ContentHasher, ExpectedHash};
+
+/// Sums the bytes at each position modulo 32, good enough to tell streams apart in tests.
+#[derive(Default)]
+struct SumHasher {
+    sums: [u8; 32],
+    len: usize,
+}
+
+impl ContentHasher for SumHasher {
+    fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            let slot = &mut self.sums[self.len % 32];
+            *slot = slot.wrapping_add(b);
+            self.len += 1;
+        }
+    }
+
+    fn finalize(&mut self) -> [u8; 32] {
+        self.sums
+    }
+}
+
+fn digest(data: &[u8]) -> [u8; 32] {
+    let mut hasher = SumHasher::default();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+#[test]
+fn matching_hash() {
+    let upper = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let expected = digest(b"HELLO WORLD");
+    let mut reader = AsyncMapReader::with_expected_hash(
+        &b"hello world"[..],
+        upper,
+        expected,
+        SumHasher::default(),
+    );
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, b"HELLO WORLD");
+}
+
+#[test]
+fn mismatched_hash() {
+    let upper = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    // The hash of the data before mapping
+    let expected = digest(b"hello world");
+    let mut reader = AsyncMapReader::with_expected_hash(
+        &b"hello world"[..],
+        upper,
+        expected,
+        SumHasher::default(),
+    );
+    let mut buf = [0; 64];
+    assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 11);
+    let err = block_on(reader.read(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // The error is only reported once
+    assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 0);
+}
+
+#[test]
+fn hash_generic_reader() {
+    let reader = (&b"hello world"[..]).map(|buf: &mut [u8]| buf.make_ascii_uppercase());
+    let mut reader = ExpectedHash::new(reader, digest(b"HELLO WORLD"), SumHasher::default());
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, b"HELLO WORLD");
+}
@@ -0,0 +1,47 @@
+use std::io;
+
+use futures_lite::{future::block_on, io::Cursor};
+
+use crate::{copy_mapped_with_progress, map_to_string};
+
+#[test]
+fn map_into_string() {
+    let cursor = Cursor::new(b"hello world".to_vec());
+    let result = block_on(map_to_string(cursor, |buf: &mut [u8]| {
+        buf.make_ascii_uppercase()
+    }));
+    assert_eq!(result.unwrap(), "HELLO WORLD");
+}
+
+#[test]
+fn map_into_string_invalid_utf8() {
+    let cursor = Cursor::new(b"hello world".to_vec());
+    let result = block_on(map_to_string(cursor, |buf: &mut [u8]| buf[0] = 0xFF));
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn copy_with_progress() {
+    let data = vec![0u8; 100_000];
+    let mut next = 0u8;
+    let increment = move |buf: &mut [u8]| {
+        for byte in buf.iter_mut() {
+            *byte = next;
+            next = next.wrapping_add(1);
+        }
+    };
+    let mut reports = Vec::new();
+    let mut out = Cursor::new(Vec::new());
+    let total = block_on(copy_mapped_with_progress(
+        Cursor::new(data),
+        &mut out,
+        increment,
+        |copied| reports.push(copied),
+    ))
+    .unwrap();
+    assert_eq!(total, 100_000);
+    assert_eq!(reports.last(), Some(&total));
+    assert!(reports.windows(2).all(|w| w[0] < w[1]), "{reports:?}");
+    let expected: Vec<u8> = (0..100_000).map(|i| i as u8).collect();
+    assert_eq!(out.into_inner(), expected);
+}
@@ -0,0 +1,25 @@
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+
+use crate::AsyncMapReader;
+
+#[test]
+fn interleave_evenly() {
+    let a = Cursor::new(b"acegi".to_vec());
+    let b = Cursor::new(b"bdfhj".to_vec());
+    let mut reader = AsyncMapReader::interleave(a, b, (1, 1), |_: &mut [u8]| {});
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"abcdefghij");
+}
+
+#[test]
+fn interleave_uneven_eof() {
+    let a = Cursor::new(b"abcdefgh".to_vec());
+    let b = Cursor::new(b"12".to_vec());
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader = AsyncMapReader::interleave(a, b, (2, 1), transformer);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    // Once `b` runs out, the rest of `a` follows
+    assert_eq!(result, b"AB1CD2EFGH");
+}
@@ -0,0 +1,37 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+
+use crate::{AsyncMapReader, ChunkReplay};
+
+fn is_error(line: &[u8]) -> bool {
+    line.windows(5).any(|w| w == b"ERROR")
+}
+
+#[test]
+fn uppercase_error_lines() {
+    let input = b"info: started\nERROR: disk full\nwarn: slow\nlast ERROR: gave up".to_vec();
+    // Lines split across reads
+    let source = ChunkReplay::new(input, [5, 12, 3, 30]);
+    let mut reader = AsyncMapReader::map_matching_lines(source, is_error, |line: &mut Vec<u8>| {
+        line.make_ascii_uppercase()
+    });
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(
+        out,
+        b"info: started\nERROR: DISK FULL\nwarn: slow\nLAST ERROR: GAVE UP"
+    );
+}
+
+#[test]
+fn long_lines() {
+    // Lines much longer than the read buffer
+    let long = "x".repeat(20_000);
+    let input = format!("{long}\nERROR {long}\n");
+    let mut reader =
+        AsyncMapReader::map_matching_lines(input.as_bytes(), is_error, |line: &mut Vec<u8>| {
+            line.truncate(5)
+        });
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, format!("{long}\nERROR\n").as_bytes());
+}
@@ -1,2 +1,35 @@
+mod adaptive;
+mod broadcast;
+mod cipher;
+#[cfg(feature = "tokio")]
+mod compat;
+mod demux;
+mod dispatch;
+mod emit;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "fs")]
+mod fs;
+mod growable;
+#[cfg(feature = "flate2")]
+mod gzip;
+mod hash;
+mod helpers;
+mod interleave;
+mod lines;
+mod padding;
+mod poll_map;
+// `buffer_size_guarantee` spells out its ceiling division
+#[allow(clippy::manual_div_ceil)]
 mod read;
+mod replay;
+mod reverse;
+mod shared;
+mod sink;
+#[cfg(feature = "stdio")]
+mod stdio;
+mod streaming;
+#[cfg(feature = "sync")]
+mod sync;
+mod verify;
 mod write;
@@ -0,0 +1,74 @@
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt, AsyncWriteExt};
+
+use crate::{AsyncMapReader, AsyncMapWriter, PaddingTransform, UnpadReader};
+
+const KEY: [u8; 4] = [0x13, 0x37, 0xc0, 0xde];
+
+// A toy block cipher, XORing each block with the key
+fn xor_blocks(buf: &mut [u8]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte ^= KEY[i % KEY.len()];
+    }
+}
+
+fn round_trip(input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    block_on(async {
+        let transform = PaddingTransform::new(KEY.len(), |buf: &mut Vec<u8>| {
+            assert_eq!(
+                buf.len() % KEY.len(),
+                0,
+                "Cipher should only see whole blocks"
+            );
+            xor_blocks(buf);
+        });
+        let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), transform, 10);
+        writer.write_all(input).await.unwrap();
+        writer.close().await.unwrap();
+        let encrypted = writer.into_inner().into_inner();
+
+        // The read buffer capacity is a multiple of the block size, so the cipher stays aligned
+        let reader = AsyncMapReader::with_capacity(Cursor::new(encrypted.clone()), xor_blocks, 8);
+        let mut reader = UnpadReader::new(reader, KEY.len());
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).await.unwrap();
+        (encrypted, decrypted)
+    })
+}
+
+#[test]
+fn padding_round_trip() {
+    let input = b"attack at dawn, bring snacks";
+    for len in 0..input.len() {
+        let (encrypted, decrypted) = round_trip(&input[..len]);
+        // PKCS#7 always adds at least one byte of padding
+        assert_eq!(encrypted.len(), (len / KEY.len() + 1) * KEY.len());
+        assert_eq!(
+            decrypted,
+            &input[..len],
+            "Round trip failed at length {len}"
+        );
+    }
+}
+
+#[test]
+fn invalid_padding() {
+    let reader = Cursor::new(b"abcdefg\x05".to_vec());
+    let mut reader = UnpadReader::new(reader, 4);
+    let mut result = Vec::new();
+    let err = block_on(reader.read_to_end(&mut result)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn padding_keeps_buffer_capacity() {
+    let transform = PaddingTransform::new(KEY.len(), |buf: &mut Vec<u8>| xor_blocks(buf));
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), transform, 64);
+    block_on(async {
+        // Leaves 3 bytes held back, prepended to the next chunk
+        writer.write_all(b"abcdefg").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"hijk").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    assert!(writer.capacity() >= 64, "{}", writer.capacity());
+}
@@ -0,0 +1,80 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_lite::{future::block_on, io::Cursor, ready, AsyncRead, AsyncReadExt};
+
+use crate::{AsyncPollMapReader, PollMapReadFn};
+
+const MARKER: u8 = b'#';
+
+// On a marker byte, pulls a key byte from the underlying reader
+// and XORs the rest of the chunk with it
+struct KeyedXor;
+
+impl<R: AsyncRead> PollMapReadFn<R> for KeyedXor {
+    fn poll_map_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        inner: Pin<&mut R>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<()>> {
+        if let Some(i) = buf.iter().position(|&b| b == MARKER) {
+            let mut key = [0u8];
+            if ready!(inner.poll_read(cx, &mut key))? == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            buf[i + 1..].iter_mut().for_each(|b| *b ^= key[0]);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+// Encodes `chunks`, keying the data after each marker with `key`.
+// Chunks with a marker must fill the reader's buffer, so the key isn't read along with them.
+fn encode(chunks: &[&[u8]], key: u8) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for chunk in chunks {
+        match chunk.iter().position(|&b| b == MARKER) {
+            Some(i) => {
+                encoded.extend_from_slice(&chunk[..=i]);
+                encoded.extend(chunk[i + 1..].iter().map(|b| b ^ key));
+                encoded.push(key);
+            }
+            None => encoded.extend_from_slice(chunk),
+        }
+    }
+    encoded
+}
+
+#[test]
+fn transform_reads_key_from_source() {
+    let chunks: [&[u8]; 4] = [b"ab#cdefg", b"hijklmno", b"#pqrstuv", b"wxyz"];
+    let encoded = encode(&chunks, 0x20);
+    assert_ne!(encoded, chunks.concat());
+
+    let mut reader = AsyncPollMapReader::with_capacity(Cursor::new(encoded), KeyedXor, 8);
+    let mut decoded = Vec::new();
+    block_on(reader.read_to_end(&mut decoded)).unwrap();
+    assert_eq!(decoded, chunks.concat());
+}
+
+#[test]
+fn transform_error_is_returned() {
+    // The key byte is missing from the end of the stream
+    let mut encoded = encode(&[b"ab#cdefg"], 0x20);
+    encoded.pop();
+
+    let mut reader = AsyncPollMapReader::with_capacity(Cursor::new(encoded), KeyedXor, 8);
+    let mut decoded = Vec::new();
+    let err = block_on(reader.read_to_end(&mut decoded)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+#[should_panic(expected = "capacity must be at least 1")]
+fn zero_capacity() {
+    let _ = AsyncPollMapReader::with_capacity(&b"abc"[..], KeyedXor, 0);
+}
@@ -1,7 +1,11 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
 
 use crate::read::{AsyncMapRead, AsyncMapReader};
-use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+use futures_lite::{future::block_on, io::Cursor, AsyncRead, AsyncReadExt};
 
 // filepath: d:/Code/Rust/async-io-map/src/test/read.rs
 
@@ -257,3 +261,1367 @@ fn buffer_size_guarantee() {
         sizes.len()
     );
 }
+
+#[test]
+fn backpressure_signal() {
+    use crate::read::{BackpressureSignal, MapReadFn};
+
+    // Flags backpressure once more than `limit` bytes have been processed
+    struct Throttled {
+        processed: usize,
+        limit: usize,
+    }
+
+    impl MapReadFn for Throttled {
+        fn map_read(&mut self, buf: &mut [u8]) {
+            self.processed += buf.len();
+        }
+
+        fn backpressure(&self) -> BackpressureSignal {
+            if self.processed > self.limit {
+                BackpressureSignal::SlowDown
+            } else {
+                BackpressureSignal::None
+            }
+        }
+    }
+
+    let cursor = Cursor::new(b"0123456789ab".to_vec());
+    let transformer = Throttled {
+        processed: 0,
+        limit: 4,
+    };
+    let mut reader = AsyncMapReader::with_capacity(cursor, transformer, 4);
+    assert!(!reader.backpressure());
+
+    block_on(async {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert!(!reader.backpressure(), "Only 4 bytes have been processed");
+        reader.read_exact(&mut buf).await.unwrap();
+        assert!(reader.backpressure(), "8 bytes have been processed");
+    });
+}
+
+#[test]
+fn stays_at_eof() {
+    // Reports EOF once, then starts producing data again
+    struct Resurrecting {
+        polls: usize,
+    }
+
+    impl AsyncRead for Resurrecting {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.polls += 1;
+            if self.polls == 2 {
+                return Poll::Ready(Ok(0));
+            }
+            buf[0] = b'x';
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(0));
+    let calls_clone = Arc::clone(&calls);
+    let transformer = move |_: &mut [u8]| *calls_clone.lock().unwrap() += 1;
+
+    let mut reader = AsyncMapReader::new(Resurrecting { polls: 0 }, transformer);
+    let mut buf = [0u8; 4];
+    block_on(async {
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 1);
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+        for _ in 0..3 {
+            let n = reader.read(&mut buf).await.unwrap();
+            assert_eq!(n, 0, "Reader should stay at EOF");
+        }
+    });
+    assert_eq!(*calls.lock().unwrap(), 1);
+    let polls = reader.into_inner().polls;
+    assert_eq!(polls, 2, "Inner reader should not be polled after EOF");
+}
+
+#[test]
+fn aligned_buffer() {
+    for align in [16, 32, 64] {
+        let data = (0..100).map(|i| i as u8).collect::<Vec<u8>>();
+        let misaligned = Arc::new(Mutex::new(false));
+        let misaligned_clone = Arc::clone(&misaligned);
+        let transformer = move |buf: &mut [u8]| {
+            if !(buf.as_ptr() as usize).is_multiple_of(align) {
+                *misaligned_clone.lock().unwrap() = true;
+            }
+            buf.iter_mut().for_each(|b| *b = b.wrapping_add(1));
+        };
+
+        let mut reader =
+            AsyncMapReader::with_aligned_capacity(Cursor::new(data), transformer, 24, align);
+        let mut result = Vec::new();
+        block_on(async {
+            reader.read_to_end(&mut result).await.unwrap();
+        });
+        assert!(result.iter().enumerate().all(|(i, &b)| b == i as u8 + 1));
+        let misaligned = *misaligned.lock().unwrap();
+        assert!(!misaligned, "Buffer was not aligned to {align} bytes");
+    }
+}
+
+#[test]
+fn transform_invocation_count() {
+    let data = (0..100).map(|i| i as u8).collect::<Vec<u8>>();
+    let mut reader = AsyncMapReader::with_capacity(Cursor::new(data), |_: &mut [u8]| {}, 8);
+    assert_eq!(reader.transform_invocations(), 0);
+
+    let mut result = Vec::new();
+    block_on(async {
+        reader.read_to_end(&mut result).await.unwrap();
+    });
+    // 12 full chunks and one partial chunk
+    assert_eq!(reader.transform_invocations(), 13);
+}
+
+#[test]
+fn toggle_transform_mid_stream() {
+    let cursor = Cursor::new(b"abcdefghijkl".to_vec());
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader = AsyncMapReader::with_capacity(cursor, transformer, 4);
+    reader.set_enabled(false);
+
+    let mut result = Vec::new();
+    block_on(async {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf).await.unwrap();
+        result.extend_from_slice(&buf);
+        // The rest of the buffered chunk was read while disabled, so it stays untransformed
+        reader.set_enabled(true);
+        reader.read_to_end(&mut result).await.unwrap();
+    });
+    assert_eq!(result, b"abcdEFGHIJKL");
+    assert_eq!(reader.transform_invocations(), 2);
+}
+
+// Reads a single byte per poll
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if self.pos == self.data.len() || buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        buf[0] = self.data[self.pos];
+        self.pos += 1;
+        Poll::Ready(Ok(1))
+    }
+}
+
+#[test]
+fn coalesce_tiny_reads() {
+    let data = (0..100).map(|i| i as u8).collect::<Vec<u8>>();
+    let sizes = Arc::new(Mutex::new(Vec::new()));
+    let sizes_clone = Arc::clone(&sizes);
+    let transformer = move |buf: &mut [u8]| sizes_clone.lock().unwrap().push(buf.len());
+
+    let trickle = Trickle {
+        data: data.clone(),
+        pos: 0,
+    };
+    let mut reader = AsyncMapReader::with_capacity(trickle, transformer, 8);
+    let mut result = Vec::new();
+    block_on(async {
+        reader.read_to_end(&mut result).await.unwrap();
+    });
+    assert_eq!(result, data);
+
+    let sizes = sizes.lock().unwrap();
+    assert_eq!(
+        sizes.len(),
+        13,
+        "Transform should be called once per full buffer"
+    );
+    assert!(sizes[..12].iter().all(|&size| size == 8));
+    assert_eq!(sizes[12], 4);
+}
+
+#[test]
+fn read_stats() {
+    use crate::MapStats;
+
+    let data = (0..20).collect::<Vec<u8>>();
+    let mut reader =
+        AsyncMapReader::with_capacity(Cursor::new(data), |_: &mut [u8]| {}, 8).with_stats();
+    let mut result = Vec::new();
+    block_on(async {
+        let mut buf = [0u8; 3];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(reader.stats().bytes_in, 8);
+        assert_eq!(reader.stats().bytes_out, 3);
+        reader.read_to_end(&mut result).await.unwrap();
+    });
+
+    let expected = MapStats {
+        bytes_in: 20,
+        bytes_out: 20,
+        transform_invocations: 3,
+        // Two full reads, then a short read followed by EOF
+        inner_calls: 4,
+        max_chunk: 8,
+    };
+    assert_eq!(reader.stats(), expected);
+}
+
+#[test]
+fn terminator() {
+    let transformer = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b = b.to_ascii_uppercase());
+    let mut reader =
+        AsyncMapReader::with_terminator(Cursor::new(b"hello".to_vec()), vec![0], transformer);
+    let mut result = Vec::new();
+    block_on(async {
+        reader.read_to_end(&mut result).await.unwrap();
+        // The reader stays at EOF afterwards
+        assert_eq!(reader.read(&mut [0u8; 4]).await.unwrap(), 0);
+    });
+    assert_eq!(result, b"HELLO\0");
+
+    let mut empty = AsyncMapReader::with_terminator(Cursor::new(vec![]), vec![0], transformer);
+    let mut result = Vec::new();
+    block_on(async {
+        empty.read_to_end(&mut result).await.unwrap();
+        assert_eq!(empty.read(&mut [0u8; 4]).await.unwrap(), 0);
+    });
+    assert_eq!(result, b"\0");
+}
+
+#[test]
+fn skip() {
+    let data = (0..20).collect::<Vec<u8>>();
+    let transformer = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 1);
+    let mut reader = AsyncMapReader::with_capacity(Cursor::new(data), transformer, 4).with_stats();
+    let mut result = Vec::new();
+    block_on(async {
+        assert_eq!(reader.skip(5).await.unwrap(), 5);
+        reader.read_to_end(&mut result).await.unwrap();
+        assert_eq!(reader.skip(5).await.unwrap(), 0);
+    });
+    assert_eq!(result, (6..=20).collect::<Vec<u8>>());
+    // The skipped bytes were mapped, and count as output
+    assert_eq!(reader.stats().transform_invocations, 5);
+    assert_eq!(reader.stats().bytes_out, 20);
+}
+
+#[test]
+fn read_to_end_lossy() {
+    // Serves 10 bytes, then fails
+    struct Flaky {
+        served: bool,
+    }
+
+    impl AsyncRead for Flaky {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            if self.served {
+                return Poll::Ready(Err(std::io::Error::other("connection lost")));
+            }
+            self.served = true;
+            let amt = buf.len().min(10);
+            buf[..amt]
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, b)| *b = i as u8);
+            Poll::Ready(Ok(amt))
+        }
+    }
+
+    let transformer = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 1);
+    let mut reader = AsyncMapReader::new(Flaky { served: false }, transformer);
+    let mut out = b"prefix".to_vec();
+    let (read, error) = block_on(reader.read_to_end_lossy(&mut out));
+
+    assert_eq!(read, 10);
+    assert_eq!(error.unwrap().to_string(), "connection lost");
+    assert_eq!(&out[..6], b"prefix");
+    assert_eq!(&out[6..], (1..=10).collect::<Vec<u8>>());
+}
+
+#[test]
+fn fixed_window() {
+    use crate::ChunkReplay;
+
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let windows_clone = Arc::clone(&windows);
+    let transformer = move |buf: &mut [u8]| {
+        windows_clone.lock().unwrap().push(buf.len());
+        buf.rotate_left(1);
+    };
+    // Chunks of 3 bytes never line up with the window on their own
+    let source = ChunkReplay::new(b"abcdefghij".to_vec(), [3, 3, 3, 1]);
+    let mut reader = AsyncMapReader::with_window(source, 4, transformer);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+
+    assert_eq!(*windows.lock().unwrap(), [4, 4, 2]);
+    assert_eq!(result, b"bcdafgheji");
+}
+
+#[test]
+fn chunk_policies() {
+    use crate::{ChunkPolicy, ChunkReplay};
+
+    let chunk_sizes = |policy: ChunkPolicy| {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let sizes_clone = Arc::clone(&sizes);
+        let transformer = move |buf: &mut [u8]| sizes_clone.lock().unwrap().push(buf.len());
+        let data = (0..10).collect::<Vec<u8>>();
+        let source = ChunkReplay::new(data.clone(), [3, 3, 3, 1]);
+        let mut reader = AsyncMapReader::with_chunk_policy(source, transformer, policy);
+        let mut result = Vec::new();
+        block_on(reader.read_to_end(&mut result)).unwrap();
+        assert_eq!(result, data);
+        let sizes = sizes.lock().unwrap().clone();
+        sizes
+    };
+
+    assert_eq!(chunk_sizes(ChunkPolicy::Fixed(4)), [4, 4, 2]);
+    assert_eq!(chunk_sizes(ChunkPolicy::RawReads), [3, 3, 3, 1]);
+    assert_eq!(chunk_sizes(ChunkPolicy::MinChunk(5)), [6, 4]);
+
+    // Raw reads aren't coalesced, even when more data is available right away
+    let sizes = Arc::new(Mutex::new(Vec::new()));
+    let sizes_clone = Arc::clone(&sizes);
+    let transformer = move |buf: &mut [u8]| sizes_clone.lock().unwrap().push(buf.len());
+    let source = Trickle {
+        data: vec![0; 5],
+        pos: 0,
+    };
+    let mut reader = AsyncMapReader::with_chunk_policy(source, transformer, ChunkPolicy::RawReads);
+    block_on(reader.read_to_end(&mut Vec::new())).unwrap();
+    assert_eq!(*sizes.lock().unwrap(), [1; 5]);
+}
+
+#[test]
+fn map_bytes() {
+    let data = (0..=254).collect::<Vec<u8>>();
+    let mut reader = AsyncMapReader::map_bytes(Cursor::new(data.clone()), |b| b + 1);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, data.iter().map(|b| b + 1).collect::<Vec<u8>>());
+}
+
+#[test]
+fn prefill_buffer() {
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let source = Cursor::new(b"def".to_vec());
+    let mut reader =
+        AsyncMapReader::with_prefill_buffer(source, transformer, b"abc".to_vec(), false);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"ABCDEF");
+
+    let source = Cursor::new(b"def".to_vec());
+    let mut reader =
+        AsyncMapReader::with_prefill_buffer(source, transformer, b"abc".to_vec(), true);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"abcDEF");
+}
+
+#[test]
+fn init_hook() {
+    use crate::MapReadFn;
+
+    // Version 1 streams are shifted by one, version 2 streams by two
+    struct Versioned {
+        shift: u8,
+        inits: usize,
+    }
+
+    impl MapReadFn for &mut Versioned {
+        fn init(&mut self, first_chunk: &[u8]) {
+            self.shift = first_chunk[0];
+            self.inits += 1;
+        }
+
+        fn map_read(&mut self, buf: &mut [u8]) {
+            buf.iter_mut().for_each(|b| *b = b.wrapping_add(self.shift));
+        }
+    }
+
+    for version in [1, 2] {
+        let mut transform = Versioned { shift: 0, inits: 0 };
+        let mut data = vec![version];
+        data.extend_from_slice(b"abcdefgh");
+        let mut reader = AsyncMapReader::with_capacity(Cursor::new(data), &mut transform, 4);
+        let mut result = Vec::new();
+        block_on(reader.read_to_end(&mut result)).unwrap();
+        drop(reader);
+
+        assert_eq!(transform.inits, 1);
+        let expected = std::iter::once(version)
+            .chain(b"abcdefgh".iter().copied())
+            .map(|b| b + version)
+            .collect::<Vec<u8>>();
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn byte_counter() {
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let data = Cursor::new(b"hello world, all is well".to_vec());
+    let mut reader = AsyncMapReader::with_byte_counter(data, b'L', transformer);
+    let mut result = Vec::new();
+    block_on(async {
+        let mut buf = [0u8; 5];
+        reader.read_exact(&mut buf).await.unwrap();
+        // Only the served bytes are counted
+        assert_eq!(reader.count(), 2);
+        result.extend_from_slice(&buf);
+        reader.read_to_end(&mut result).await.unwrap();
+    });
+    assert_eq!(result, b"HELLO WORLD, ALL IS WELL");
+    assert_eq!(reader.count(), 7);
+}
+
+#[test]
+fn ascii_guard() {
+    let data = b"plain text".to_vec();
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader = AsyncMapReader::new(Cursor::new(data.clone()), transformer).with_ascii_guard();
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"PLAIN TEXT");
+
+    let transformer = |buf: &mut [u8]| buf[3] |= 0x80;
+    let mut reader = AsyncMapReader::new(Cursor::new(data), transformer).with_ascii_guard();
+    let err = block_on(reader.read(&mut [0u8; 16])).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn snapshot_restore() {
+    use futures_lite::AsyncBufReadExt;
+
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let data = Cursor::new(b"abcdefgh".to_vec());
+    let mut reader = AsyncMapReader::with_capacity(data, transformer, 4);
+    block_on(async {
+        reader.fill_buf().await.unwrap();
+        let snapshot = reader.snapshot();
+        let mut buf = [0u8; 3];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ABC");
+
+        // Roll back and read the same bytes again
+        assert!(reader.restore(snapshot));
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ABC");
+
+        // The snapshot is useless once the buffer is refilled
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"DEF");
+        assert!(!reader.restore(snapshot));
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"GH");
+    });
+}
+
+#[test]
+fn retry_incomplete_units() {
+    use crate::{ChunkReplay, MapReadFn, MapReadStatus};
+
+    // Reverses 5-byte units, which it can't do on partial units
+    struct Units {
+        seen: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl MapReadFn for Units {
+        fn map_read(&mut self, buf: &mut [u8]) {
+            buf.chunks_mut(5).for_each(|unit| unit.reverse());
+        }
+
+        fn map_read_checked(&mut self, buf: &mut [u8]) -> MapReadStatus {
+            if !buf.len().is_multiple_of(5) {
+                return MapReadStatus::Incomplete;
+            }
+            self.seen.lock().unwrap().push(buf.len());
+            self.map_read(buf);
+            MapReadStatus::Complete
+        }
+    }
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let transform = Units {
+        seen: Arc::clone(&seen),
+    };
+    // 3 bytes, then 2 bytes, then a whole unit
+    let source = ChunkReplay::new(b"abcdefghij".to_vec(), [3, 2, 5]);
+    let mut reader = AsyncMapReader::with_capacity(source, transform, 4).with_max_capacity(8);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"edcbajihgf");
+    assert_eq!(*seen.lock().unwrap(), [5, 5]);
+
+    // A unit cut short by EOF
+    let transform = Units {
+        seen: Arc::new(Mutex::new(Vec::new())),
+    };
+    let mut reader = AsyncMapReader::new(Cursor::new(b"abcdefg".to_vec()), transform);
+    let err = block_on(reader.read_to_end(&mut Vec::new())).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn capacity_rounded_to_required_multiple() {
+    use crate::MapReadFn;
+
+    struct BlockCipher {
+        chunks: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl MapReadFn for BlockCipher {
+        fn map_read(&mut self, buf: &mut [u8]) {
+            self.chunks.lock().unwrap().push(buf.len());
+        }
+
+        fn required_multiple(&self) -> usize {
+            16
+        }
+    }
+
+    let chunks = Arc::new(Mutex::new(Vec::new()));
+    let transform = BlockCipher {
+        chunks: Arc::clone(&chunks),
+    };
+    let mut reader = AsyncMapReader::with_capacity(Cursor::new(vec![0; 80]), transform, 20);
+    assert_eq!(reader.capacity(), 32);
+    block_on(reader.read_to_end(&mut Vec::new())).unwrap();
+    assert_eq!(*chunks.lock().unwrap(), [32, 32, 16]);
+
+    let reader = AsyncMapReader::with_capacity(Cursor::new(vec![]), |_: &mut [u8]| {}, 20);
+    assert_eq!(reader.capacity(), 20);
+}
+
+#[test]
+fn from_mapped() {
+    let data = (0..100_000).map(|i| i as u8).collect::<Vec<u8>>();
+    let transformer = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b = b.wrapping_add(1));
+    let mut reader = AsyncMapReader::from_mapped(&data, transformer);
+    let mut result = Vec::new();
+    block_on(async {
+        let mut buf = [0u8; 1000];
+        loop {
+            let n = reader.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            // Never more than a chunk is buffered
+            assert!(reader.capacity() < data.len());
+            result.extend_from_slice(&buf[..n]);
+        }
+    });
+    assert_eq!(reader.transform_invocations(), 100_000_u64.div_ceil(8192));
+    let expected = data.iter().map(|b| b.wrapping_add(1)).collect::<Vec<u8>>();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn replacement() {
+    let clean = |b: u8| {
+        if b.is_ascii() {
+            Ok(b.to_ascii_lowercase())
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "not ASCII",
+            ))
+        }
+    };
+    let data = Cursor::new(b"Caf\xC3\xA9 OK".to_vec());
+    let mut reader = AsyncMapReader::with_replacement(data, b'?', clean);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"caf?? ok");
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn poisoned_tail() {
+    use crate::read::POISON;
+    use futures_lite::AsyncBufReadExt;
+
+    // A short read leaves most of the buffer unused
+    let mut reader =
+        AsyncMapReader::with_capacity(Cursor::new(b"abc".to_vec()), |_: &mut [u8]| {}, 16);
+    block_on(async {
+        assert_eq!(reader.fill_buf().await.unwrap(), b"abc");
+    });
+    let tail = reader.unused_buffer();
+    assert_eq!(tail.len(), 13);
+    assert!(tail.iter().all(|&b| b == POISON));
+}
+
+#[test]
+fn read_exact_transformed() {
+    use crate::ChunkReplay;
+
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    // Chunks much smaller than what's asked for
+    let source = ChunkReplay::new(b"abcdefgh".to_vec(), [2, 1, 2, 3]);
+    let mut reader = AsyncMapReader::new(source, transformer);
+    let mut buf = [0u8; 5];
+    block_on(reader.read_exact_transformed(&mut buf)).unwrap();
+    assert_eq!(&buf, b"ABCDE");
+
+    // Only 3 bytes are left
+    let err = block_on(reader.read_exact_transformed(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn fallible_mapping() {
+    use crate::{ChunkReplay, TryMapReadFn};
+
+    // Drops 'x' bytes, and fails on '!'
+    struct Filter;
+
+    impl TryMapReadFn for Filter {
+        fn try_map_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if buf.contains(&b'!') {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "corrupt chunk",
+                ));
+            }
+            let mut len = 0;
+            for i in 0..buf.len() {
+                if buf[i] != b'x' {
+                    buf[len] = buf[i];
+                    len += 1;
+                }
+            }
+            Ok(len)
+        }
+    }
+
+    let source = ChunkReplay::new(b"axbxxxx!!cdx".to_vec(), [3, 4, 2, 3]);
+    let mut reader = AsyncMapReader::try_new(source, Filter);
+    let mut buf = [0; 8];
+    assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 2);
+    assert_eq!(&buf[..2], b"ab");
+    // The chunk made of 'x' only is skipped, then the corrupt one fails
+    let err = block_on(reader.read(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // The failed chunk isn't mapped again
+    let mut rest = Vec::new();
+    block_on(reader.read_to_end(&mut rest)).unwrap();
+    assert_eq!(rest, b"cd");
+
+    // Infallible closures still work
+    let mut reader = AsyncMapReader::try_new(&b"abc"[..], |buf: &mut [u8]| buf.reverse());
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, b"cba");
+}
+
+#[test]
+fn validate_mapped_chunks() {
+    use crate::ChunkReplay;
+
+    // Turns '0' into a NUL byte
+    let transformer = |buf: &mut [u8]| buf.iter_mut().filter(|b| **b == b'0').for_each(|b| *b = 0);
+    let source = ChunkReplay::new(b"abc1230def".to_vec(), [3, 4, 3]);
+    let mut reader = AsyncMapReader::new(source, transformer).validate(|chunk| {
+        if chunk.contains(&0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "NUL byte in the output",
+            ));
+        }
+        Ok(())
+    });
+    let mut buf = [0; 16];
+    assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 3);
+    assert_eq!(&buf[..3], b"abc");
+    let err = block_on(reader.read(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // The offending chunk is skipped
+    assert_eq!(block_on(reader.read(&mut buf)).unwrap(), 3);
+    assert_eq!(&buf[..3], b"def");
+}
+
+#[test]
+fn inner_accessors() {
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader = AsyncMapReader::with_capacity(Cursor::new(b"abcdef".to_vec()), transformer, 4);
+    let mut buf = [0; 2];
+    block_on(reader.read_exact(&mut buf)).unwrap();
+    // A whole chunk was pulled from the underlying reader
+    assert_eq!(reader.get_ref().position(), 4);
+    reader.get_mut().set_position(5);
+    let mut rest = Vec::new();
+    block_on(reader.read_to_end(&mut rest)).unwrap();
+    assert_eq!(rest, b"CDF");
+
+    let mut reader = Box::pin(reader);
+    assert_eq!(reader.as_mut().get_pin_mut().position(), 6);
+}
+
+#[test]
+fn hot_path_matches_reference() {
+    use crate::ChunkReplay;
+
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let expected: Vec<u8> = data.iter().map(|b| b.rotate_left(3) ^ 0x5A).collect();
+    let transform = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b = b.rotate_left(3) ^ 0x5A);
+    // Every read size, from byte at a time to larger than the buffer
+    for read_size in [1, 7, 64, 1000, 5000] {
+        let source = ChunkReplay::new(data.clone(), [1, 300, 4096, 17]);
+        let mut reader = AsyncMapReader::with_capacity(source, transform, 1024);
+        let mut out = Vec::new();
+        let mut buf = vec![0; read_size];
+        loop {
+            let n = block_on(reader.read(&mut buf)).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(out, expected, "read size {read_size}");
+    }
+}
+
+#[test]
+fn seek_discards_buffer() {
+    use futures_lite::{io::SeekFrom, AsyncSeekExt};
+
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let source = Cursor::new(b"abcdefghijkl".to_vec());
+    let mut reader = AsyncMapReader::with_capacity(source, transformer, 8);
+    let mut buf = [0; 5];
+    block_on(async {
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ABCDE");
+        // Back by 3 from the logical position, although 8 bytes were pulled from the source
+        assert_eq!(reader.seek(SeekFrom::Current(-3)).await.unwrap(), 2);
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"CDEFG");
+        assert_eq!(reader.seek(SeekFrom::Start(0)).await.unwrap(), 0);
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, b"ABCDEFGHIJKL");
+    });
+}
+
+#[test]
+fn failed_seek_keeps_buffer() {
+    use futures_lite::{io::SeekFrom, AsyncSeek, AsyncSeekExt};
+
+    // Refuses every seek
+    struct Unseekable(Cursor<Vec<u8>>);
+
+    impl AsyncRead for Unseekable {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.0).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncSeek for Unseekable {
+        fn poll_seek(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _pos: SeekFrom,
+        ) -> Poll<std::io::Result<u64>> {
+            Poll::Ready(Err(std::io::ErrorKind::Unsupported.into()))
+        }
+    }
+
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let source = Unseekable(Cursor::new(b"abcdefghijkl".to_vec()));
+    let mut reader = AsyncMapReader::with_capacity(source, transformer, 8);
+    block_on(async {
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ABC");
+        let err = reader.seek(SeekFrom::Start(0)).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"DEFGHIJKL");
+    });
+}
+
+#[test]
+fn seek_after_eof_restarts_stream() {
+    use crate::MapReadFn;
+    use futures_lite::{io::SeekFrom, AsyncSeekExt};
+
+    // Uppercases the data, and ends it with a footer
+    struct Footed;
+
+    impl MapReadFn for Footed {
+        fn map_read(&mut self, buf: &mut [u8]) {
+            buf.make_ascii_uppercase();
+        }
+
+        fn finalize(&mut self, out: &mut Vec<u8>) {
+            out.push(b'#');
+        }
+    }
+
+    let segments = Arc::new(Mutex::new(Vec::new()));
+    let segments_clone = Arc::clone(&segments);
+    let source = Cursor::new(b"abcdef".to_vec());
+    let mut reader = AsyncMapReader::with_terminator(source, b"\n".to_vec(), Footed)
+        .with_limit(7)
+        .with_segmenter(4, move |index, segment| {
+            segments_clone
+                .lock()
+                .unwrap()
+                .push((index, segment.to_vec()));
+        });
+    block_on(async {
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, b"ABCDEF#");
+        // A partial read, leaving a segment pending
+        reader.seek(SeekFrom::Start(0)).await.unwrap();
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ABC");
+        // The footer, limit and segments all start over with the stream
+        reader.seek(SeekFrom::Start(2)).await.unwrap();
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, b"CDEF#\n");
+    });
+    assert_eq!(
+        *segments.lock().unwrap(),
+        [
+            (0, b"ABCD".to_vec()),
+            (1, b"EF#".to_vec()),
+            (0, b"CDEF".to_vec()),
+            (1, b"#\n".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn footer_after_body() {
+    use crate::{ChunkReplay, MapReadFn};
+
+    // Records the offset of every chunk, and lists them in the footer
+    #[derive(Default)]
+    struct Index {
+        offset: usize,
+        offsets: Vec<usize>,
+    }
+
+    impl MapReadFn for Index {
+        fn map_read(&mut self, buf: &mut [u8]) {
+            self.offsets.push(self.offset);
+            self.offset += buf.len();
+        }
+
+        fn finalize(&mut self, out: &mut Vec<u8>) {
+            let offsets: Vec<_> = self.offsets.iter().map(|o| o.to_string()).collect();
+            out.extend_from_slice(format!("|{}", offsets.join(",")).as_bytes());
+        }
+    }
+
+    let source = ChunkReplay::new(b"aaabbbbcc".to_vec(), [3, 4, 2]);
+    let mut reader = AsyncMapReader::with_terminator(source, b"\n".to_vec(), Index::default());
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, b"aaabbbbcc|0,3,7\n");
+    // The footer isn't served again
+    assert_eq!(block_on(reader.read(&mut [0; 8])).unwrap(), 0);
+}
+
+#[test]
+fn stateful_carry_over() {
+    use crate::ChunkReplay;
+    use std::{cell::RefCell, rc::Rc};
+
+    let chunks = Rc::new(RefCell::new(Vec::new()));
+    let seen = Rc::clone(&chunks);
+    // Reverses 3-byte groups, leaving any partial group for later
+    let groups = move |buf: &mut [u8]| {
+        let len = buf.len() / 3 * 3;
+        buf[..len].chunks_mut(3).for_each(|group| group.reverse());
+        seen.borrow_mut().push(len);
+        len
+    };
+    let source = ChunkReplay::new(b"abcdefghijkl".to_vec(), [2, 5, 1, 4]);
+    let mut reader = AsyncMapReader::stateful_with_capacity(source, groups, 5);
+    let mut out = Vec::new();
+    block_on(reader.read_to_end(&mut out)).unwrap();
+    assert_eq!(out, b"cbafedihglkj");
+    // Only whole groups were ever mapped
+    assert!(chunks.borrow().iter().all(|len| len % 3 == 0));
+
+    // A partial group at the end of the stream
+    let groups = |buf: &mut [u8]| buf.len() / 3 * 3;
+    let mut reader = AsyncMapReader::stateful_with_capacity(&b"abcdefgh"[..], groups, 5);
+    let mut out = Vec::new();
+    let err = block_on(reader.read_to_end(&mut out)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(out, b"abcdef");
+}
+
+#[test]
+fn capacity_filled() {
+    use crate::ChunkReplay;
+
+    let sizes = Arc::new(Mutex::new(Vec::new()));
+    let sizes_clone = Arc::clone(&sizes);
+    let transformer = move |buf: &mut [u8]| sizes_clone.lock().unwrap().push(buf.len());
+    // One byte per poll, with the reader blocking in between
+    let data: Vec<u8> = (0..20).collect();
+    let source = ChunkReplay::new(data.clone(), [1; 20]);
+    let mut reader = AsyncMapReader::with_capacity_filled(source, transformer, 8);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, data);
+    assert_eq!(*sizes.lock().unwrap(), [8, 8, 4]);
+}
+
+#[test]
+fn aligned_chunks() {
+    use crate::ChunkReplay;
+
+    for len in [10u8, 12] {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let sizes_clone = Arc::clone(&sizes);
+        let transformer = move |buf: &mut [u8]| sizes_clone.lock().unwrap().push(buf.len());
+        let data: Vec<u8> = (0..len).collect();
+        // Reads that cut through the groups
+        let source = ChunkReplay::new(data.clone(), [3, 2, 6]);
+        let mut reader = source.map_aligned(transformer, 8, 4);
+        let mut result = Vec::new();
+        block_on(reader.read_to_end(&mut result)).unwrap();
+        assert_eq!(result, data);
+        let sizes = sizes.lock().unwrap();
+        let (last, rest) = sizes.split_last().unwrap();
+        assert!(rest.iter().all(|size| size % 4 == 0), "{sizes:?}");
+        assert_eq!(*last % 4, len as usize % 4);
+    }
+}
+
+#[test]
+#[should_panic(expected = "alignment must not exceed the capacity")]
+fn alignment_larger_than_capacity() {
+    let _ = (&b""[..]).map_aligned(|_: &mut [u8]| {}, 4, 8);
+}
+
+#[test]
+fn unboxed_mapping_function() {
+    fn upper(buf: &mut [u8]) {
+        buf.make_ascii_uppercase()
+    }
+
+    let mut reader: AsyncMapReader<'_, _, fn(&mut [u8])> = (&b"hello"[..]).map(upper);
+    let mut result = String::new();
+    block_on(reader.read_to_string(&mut result)).unwrap();
+    assert_eq!(result, "HELLO");
+
+    let mut boxed: crate::BoxedAsyncMapReader<'_, _> = AsyncMapReader::new(&b"hello"[..], upper);
+    let mut result = String::new();
+    block_on(boxed.read_to_string(&mut result)).unwrap();
+    assert_eq!(result, "HELLO");
+}
+
+#[test]
+fn into_parts_keeps_buffered_bytes() {
+    let data: Vec<u8> = (0..100).collect();
+    let mut reader = AsyncMapReader::with_capacity(
+        Cursor::new(data),
+        |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 1),
+        32,
+    );
+    let mut head = [0; 10];
+    block_on(reader.read_exact(&mut head)).unwrap();
+    assert_eq!(head, *b"\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a");
+
+    let (mut inner, buffered) = reader.into_parts();
+    assert_eq!(buffered, (11..=32).collect::<Vec<u8>>());
+    let mut rest = Vec::new();
+    block_on(inner.read_to_end(&mut rest)).unwrap();
+    assert_eq!(rest, (32..100).collect::<Vec<u8>>());
+}
+
+#[test]
+fn overlapping_windows() {
+    use crate::ChunkReplay;
+
+    // Averages each byte with its predecessor, which is only known at chunk boundaries through the overlap
+    fn smooth(buf: &mut [u8]) {
+        for i in (1..buf.len()).rev() {
+            buf[i] = ((buf[i - 1] as u16 + buf[i] as u16) / 2) as u8;
+        }
+    }
+
+    let data: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(37)).collect();
+    let mut expected = data.clone();
+    smooth(&mut expected);
+
+    let source = ChunkReplay::new(data, [3, 1, 7, 5]);
+    let mut reader = AsyncMapReader::with_overlap(source, 2, smooth);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn transform_retry_limit() {
+    use crate::{MapReadFn, MapReadStatus};
+
+    struct NeverComplete(Arc<Mutex<usize>>);
+
+    impl MapReadFn for NeverComplete {
+        fn map_read(&mut self, _buf: &mut [u8]) {}
+
+        fn map_read_checked(&mut self, _buf: &mut [u8]) -> MapReadStatus {
+            *self.0.lock().unwrap() += 1;
+            MapReadStatus::Incomplete
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(0));
+    let data = vec![0; 1024];
+    let mut reader =
+        AsyncMapReader::with_capacity(Cursor::new(data), NeverComplete(Arc::clone(&calls)), 4)
+            .with_max_capacity(1024)
+            .max_transform_retries(3);
+    let mut result = Vec::new();
+    let err = block_on(reader.read_to_end(&mut result)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    assert_eq!(*calls.lock().unwrap(), 4);
+}
+
+#[test]
+fn peek_does_not_consume() {
+    use crate::ChunkReplay;
+
+    let data: Vec<u8> = (0..50).collect();
+    let source = ChunkReplay::new(data.clone(), [3, 4, 20]);
+    let mut reader = AsyncMapReader::with_capacity(
+        source,
+        |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 100),
+        16,
+    );
+    let expected: Vec<u8> = data.iter().map(|b| b + 100).collect();
+    block_on(async {
+        // Needs several fills
+        assert_eq!(reader.peek(10).await.unwrap(), &expected[..10]);
+        assert_eq!(reader.peek(4).await.unwrap(), &expected[..4]);
+        let mut head = [0; 2];
+        reader.read_exact(&mut head).await.unwrap();
+        assert_eq!(head, expected[..2]);
+        // Larger than the capacity
+        assert_eq!(reader.peek(100).await.unwrap(), &expected[2..18]);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, expected[2..]);
+        assert!(reader.peek(1).await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn clone_mid_stream() {
+    let data: Vec<u8> = (0..100).collect();
+    let mut offset = 0u8;
+    // A stateful mapping function, whose state must be cloned along with the buffer
+    let shift = move |buf: &mut [u8]| {
+        for b in buf.iter_mut() {
+            *b = b.wrapping_add(offset);
+            offset = offset.wrapping_add(1);
+        }
+    };
+    let mut reader = AsyncMapReader::from_fn_with_capacity(Cursor::new(data), shift, 16);
+    let mut head = [0; 20];
+    block_on(reader.read_exact(&mut head)).unwrap();
+
+    let mut clone = reader.clone();
+    let mut first = Vec::new();
+    block_on(reader.read_to_end(&mut first)).unwrap();
+    let mut second = Vec::new();
+    block_on(clone.read_to_end(&mut second)).unwrap();
+    assert_eq!(first.len(), 80);
+    assert_eq!(first, second);
+    assert_eq!(first[0], 40);
+}
+
+#[test]
+fn debug_output() {
+    let reader = AsyncMapReader::with_capacity(Cursor::new(vec![1, 2, 3]), |_: &mut [u8]| {}, 64);
+    let debug = format!("{reader:?}");
+    assert!(debug.starts_with("AsyncMapReader"), "{debug}");
+    assert!(debug.contains("capacity: 64"), "{debug}");
+}
+
+#[test]
+fn capacity_and_buffered() {
+    let mut reader =
+        AsyncMapReader::with_capacity(Cursor::new(vec![0; 100]), |_: &mut [u8]| {}, 32);
+    assert_eq!(reader.capacity(), 32);
+    assert_eq!(reader.buffered(), 0);
+    let mut head = [0; 10];
+    block_on(reader.read_exact(&mut head)).unwrap();
+    assert_eq!(reader.capacity(), 32);
+    assert_eq!(reader.buffered(), 22);
+}
+
+#[test]
+fn output_history() {
+    use crate::ChunkReplay;
+
+    let data: Vec<u8> = (0..60u8).map(|i| i.wrapping_mul(91)).collect();
+    // Chains every byte to the previous output byte
+    let encode = |history: &[u8], buf: &mut [u8]| {
+        let mut last = history.last().copied().unwrap_or(0);
+        for b in buf.iter_mut() {
+            *b ^= last;
+            last = *b;
+        }
+    };
+    let source = ChunkReplay::new(data.clone(), [5, 1, 9, 30]);
+    let mut encoder = AsyncMapReader::with_output_history(source, 1, encode);
+    let mut encoded = Vec::new();
+    block_on(encoder.read_to_end(&mut encoded)).unwrap();
+    assert_ne!(encoded, data);
+
+    // Decoding only needs the previous input byte
+    let decode = |buf: &mut [u8]| {
+        for i in (1..buf.len()).rev() {
+            buf[i] ^= buf[i - 1];
+        }
+    };
+    let source = ChunkReplay::new(encoded, [7, 2, 40, 11]);
+    let mut decoder = AsyncMapReader::with_overlap(source, 1, decode);
+    let mut decoded = Vec::new();
+    block_on(decoder.read_to_end(&mut decoded)).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+#[should_panic(expected = "capacity must be at least 1")]
+fn zero_capacity() {
+    let _ = (&b"abc"[..]).map_with_capacity(|_: &mut [u8]| {}, 0);
+}
+
+#[test]
+fn capacity_of_one() {
+    let mut reader =
+        (&b"abc"[..]).map_with_capacity(|buf: &mut [u8]| buf.make_ascii_uppercase(), 1);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"ABC");
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn zeroize_buffer() {
+    use crate::buffer::Buffer;
+
+    let mut reader =
+        AsyncMapReader::with_capacity(Cursor::new(b"secret".to_vec()), |_: &mut [u8]| {}, 16)
+            .with_zeroize();
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"secret");
+    drop(reader);
+
+    // What dropping a buffer holding data does
+    let mut buffer = Buffer::aligned(16, 8);
+    buffer[..6].copy_from_slice(b"secret");
+    buffer.zeroize = true;
+    buffer.wipe();
+    assert!(buffer.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn last_error() {
+    // Always fails
+    struct Broken;
+
+    impl AsyncRead for Broken {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset",
+            )))
+        }
+    }
+
+    let mut reader = AsyncMapReader::new(Broken, |_: &mut [u8]| {});
+    assert!(reader.last_error().is_none());
+    let mut buf = [0; 8];
+    let err = block_on(reader.read(&mut buf)).unwrap_err();
+    let last = reader.last_error().unwrap();
+    assert_eq!(last.kind(), err.kind());
+    assert_eq!(last.to_string(), err.to_string());
+}
+
+#[test]
+fn progress_counts_raw_bytes() {
+    use crate::TryMapReadFn;
+
+    // Keeps only the first half of every chunk
+    struct Halve;
+
+    impl TryMapReadFn for Halve {
+        fn try_map_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            Ok(buf.len() / 2)
+        }
+    }
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let progress_clone = Arc::clone(&progress);
+    let mut reader = AsyncMapReader::try_with_capacity(Cursor::new(vec![0; 100]), Halve, 8)
+        .on_progress(move |n| progress_clone.lock().unwrap().push(n));
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result.len(), 50);
+    let progress = progress.lock().unwrap();
+    assert!(progress.iter().all(|&n| n <= 8), "{progress:?}");
+    assert_eq!(progress.iter().sum::<usize>(), 100);
+}
+
+#[test]
+fn limit_within_buffered_chunk() {
+    let cursor = Cursor::new(b"hello world".to_vec());
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader = AsyncMapReader::with_capacity(cursor, transformer, 8).with_limit(5);
+    let mut result = Vec::new();
+    block_on(async {
+        let mut buf = [0u8; 3];
+        loop {
+            let n = reader.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            result.extend_from_slice(&buf[..n]);
+        }
+        // Still at EOF on further reads, although the chunk isn't consumed
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+        assert_eq!(reader.peek(4).await.unwrap(), b"");
+    });
+    assert_eq!(result, b"HELLO");
+}
+
+#[test]
+fn limit_counts_served_bytes() {
+    // The terminator lengthens the mapped data past what the underlying reader returns
+    let cursor = Cursor::new(b"abc".to_vec());
+    let transformer = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader =
+        AsyncMapReader::with_terminator(cursor, b"\r\n".to_vec(), transformer).with_limit(4);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"ABC\r");
+
+    let cursor = Cursor::new(b"abcdef".to_vec());
+    let mut reader = AsyncMapReader::with_capacity(cursor, transformer, 4).with_limit(5);
+    let mut buf = [0u8; 6];
+    let err = block_on(reader.read_exact_transformed(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn segmenter_splits_served_data() {
+    let segments = Arc::new(Mutex::new(Vec::new()));
+    let segments_clone = Arc::clone(&segments);
+    let cursor = Cursor::new(b"0123456789".to_vec());
+    let transformer = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 17);
+    let mut reader = AsyncMapReader::with_capacity(cursor, transformer, 3).with_segmenter(
+        4,
+        move |index, segment| {
+            segments_clone
+                .lock()
+                .unwrap()
+                .push((index, segment.to_vec()));
+        },
+    );
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"ABCDEFGHIJ");
+    let segments = segments.lock().unwrap();
+    assert_eq!(
+        *segments,
+        [
+            (0, b"ABCD".to_vec()),
+            (1, b"EFGH".to_vec()),
+            (2, b"IJ".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn retry_incomplete_aligned_chunk() {
+    use crate::{ChunkReplay, MapReadFn, MapReadStatus};
+
+    // Needs at least 7 bytes to map a chunk, unless it's the last one
+    struct AtLeastSeven;
+
+    impl MapReadFn for AtLeastSeven {
+        fn map_read(&mut self, buf: &mut [u8]) {
+            buf.make_ascii_uppercase();
+        }
+
+        fn map_read_checked(&mut self, buf: &mut [u8]) -> MapReadStatus {
+            if buf.len() < 7 {
+                return MapReadStatus::Incomplete;
+            }
+            self.map_read(buf);
+            MapReadStatus::Complete
+        }
+    }
+
+    // `e` is held back for alignment while `abcd` is retried, then both must be kept
+    let source = ChunkReplay::new(b"abcdefgh".to_vec(), [5, 3]);
+    let mut reader = AsyncMapReader::with_alignment(source, AtLeastSeven, 16, 4);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"ABCDEFGH");
+}
+
+#[test]
+fn reader_with_callbacks_is_send() {
+    fn is_send<T: Send>(_: &T) {}
+
+    let reader = AsyncMapReader::from_fn(Cursor::new(b"abc".to_vec()), |buf: &mut [u8]| {
+        buf.make_ascii_uppercase()
+    })
+    .validate(|_| Ok(()))
+    .on_progress(|_| {})
+    .with_segmenter(2, |_, _| {});
+    is_send(&reader);
+    let handle = std::thread::spawn(move || {
+        let mut reader = reader;
+        let mut out = Vec::new();
+        block_on(reader.read_to_end(&mut out)).unwrap();
+        out
+    });
+    assert_eq!(handle.join().unwrap(), b"ABC");
+}
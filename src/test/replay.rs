@@ -0,0 +1,42 @@
+use std::sync::{Arc, Mutex};
+
+use futures_lite::{future::block_on, AsyncReadExt};
+
+use crate::{AsyncMapReader, ChunkReplay};
+
+#[test]
+fn record_and_replay() {
+    let data = (0..40).collect::<Vec<u8>>();
+
+    // Original run, with irregular boundaries coming from the source
+    let source = ChunkReplay::new(data.clone(), [3, 7, 1, 12, 5]);
+    let mut recorder =
+        AsyncMapReader::with_capacity(source, |buf: &mut [u8]| buf.reverse(), 16).record_chunks();
+    let mut original = Vec::new();
+    block_on(recorder.read_to_end(&mut original)).unwrap();
+    let lengths = recorder.recorded_chunks().to_vec();
+    assert_eq!(lengths, [3, 7, 1, 12, 5, 12]);
+
+    // Replaying the recorded boundaries reproduces the exact same chunks
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_clone = Arc::clone(&seen);
+    let transformer = move |buf: &mut [u8]| {
+        seen_clone.lock().unwrap().push(buf.len());
+        buf.reverse();
+    };
+    let mut replay = AsyncMapReader::replay_chunks(data, lengths.clone(), transformer);
+    let mut output = Vec::new();
+    block_on(replay.read_to_end(&mut output)).unwrap();
+    assert_eq!(output, original);
+    assert_eq!(*seen.lock().unwrap(), lengths);
+}
+
+#[test]
+fn not_recording_by_default() {
+    let source = ChunkReplay::new(b"data".to_vec(), [2]);
+    let mut reader = AsyncMapReader::new(source, |_: &mut [u8]| {});
+    let mut output = Vec::new();
+    block_on(reader.read_to_end(&mut output)).unwrap();
+    assert_eq!(output, b"data");
+    assert!(reader.recorded_chunks().is_empty());
+}
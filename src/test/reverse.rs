@@ -0,0 +1,25 @@
+use std::sync::{Arc, Mutex};
+
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+
+use crate::AsyncMapReader;
+
+#[test]
+fn chunks_in_reverse_order() {
+    let chunks = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&chunks);
+    let transformer = move |buf: &mut [u8]| {
+        seen.lock().unwrap().push(buf.to_vec());
+        buf.make_ascii_uppercase();
+    };
+    let data = Cursor::new(b"abcdefghij".to_vec());
+    let mut reader = AsyncMapReader::reverse_chunks(data, transformer, 4);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+
+    assert_eq!(
+        *chunks.lock().unwrap(),
+        [b"ghij".to_vec(), b"cdef".to_vec(), b"ab".to_vec()]
+    );
+    assert_eq!(result, b"GHIJCDEFAB");
+}
@@ -0,0 +1,203 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures_lite::{future::block_on, io, AsyncReadExt, AsyncWrite};
+
+use crate::{AsyncMapReader, AsyncMapWriter, ChunkReplay, SharedReader, SharedWriter};
+
+#[test]
+fn readers_share_a_source() {
+    let data = (0..100).collect::<Vec<u8>>();
+    // Yield after every 3 bytes, so reads don't always fill the buffer
+    let source = Arc::new(Mutex::new(ChunkReplay::new(data.clone(), vec![3; 33])));
+
+    let transformer = |buf: &mut [u8]| buf.iter_mut().for_each(|b| *b += 1);
+    let mut a = AsyncMapReader::from_shared(Arc::clone(&source), transformer);
+    let mut b = AsyncMapReader::from_shared(Arc::clone(&source), transformer);
+
+    let mut output = Vec::new();
+    block_on(async {
+        let mut buf = [0u8; 8];
+        loop {
+            let n = a.read(&mut buf).await.unwrap();
+            assert!(source.try_lock().is_ok(), "Lock held after a read");
+            output.extend_from_slice(&buf[..n]);
+            let m = b.read(&mut buf).await.unwrap();
+            assert!(source.try_lock().is_ok(), "Lock held after a read");
+            output.extend_from_slice(&buf[..m]);
+            if n == 0 && m == 0 {
+                break;
+            }
+        }
+    });
+
+    // Both readers took turns, and every byte was read exactly once
+    let expected = data.iter().map(|b| b + 1).collect::<Vec<u8>>();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn shared_reader_clone() {
+    let source = Arc::new(Mutex::new(futures_lite::io::Cursor::new(b"abcd".to_vec())));
+    let reader = SharedReader::new(source);
+    let clone = reader.clone();
+    assert!(Arc::ptr_eq(reader.shared(), clone.shared()));
+}
+
+// Accepts at most 2 bytes per write, and is pending every other call
+struct Slow {
+    out: Vec<u8>,
+    ready: bool,
+}
+
+impl AsyncWrite for Slow {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let n = buf.len().min(2);
+        self.out.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn shared_writer_messages_dont_interleave() {
+    use futures_lite::future;
+
+    let slow = Slow {
+        out: Vec::new(),
+        ready: false,
+    };
+    let writer = SharedWriter::new(AsyncMapWriter::with_capacity(
+        slow,
+        |buf: &mut Vec<u8>| buf.make_ascii_uppercase(),
+        4,
+    ));
+    let a = writer.clone();
+    let b = writer.clone();
+    block_on(future::zip(
+        async {
+            for _ in 0..3 {
+                a.write_message(b"aaaaaaaaaa\n").await.unwrap();
+            }
+        },
+        async {
+            for _ in 0..3 {
+                b.write_message(b"bbbbbbbbbb\n").await.unwrap();
+            }
+        },
+    ));
+    drop((a, b));
+    let out = writer.into_inner().unwrap().into_inner().out;
+    let lines: Vec<&[u8]> = out
+        .split(|&b| b == b'\n')
+        .filter(|l| !l.is_empty())
+        .collect();
+    assert_eq!(lines.len(), 6);
+    for line in lines {
+        assert!(line == b"AAAAAAAAAA" || line == b"BBBBBBBBBB", "{line:?}");
+    }
+}
+
+#[test]
+fn shared_writer_across_threads() {
+    let slow = Slow {
+        out: Vec::new(),
+        ready: false,
+    };
+    let writer = SharedWriter::new(AsyncMapWriter::from_fn_with_capacity(
+        slow,
+        |buf: &mut Vec<u8>| buf.make_ascii_uppercase(),
+        4,
+    ));
+    let handles: Vec<_> = [b"aaaaaaaaaa\n", b"bbbbbbbbbb\n"]
+        .into_iter()
+        .map(|message| {
+            let writer = writer.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    block_on(writer.write_message(message)).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let out = writer.into_inner().unwrap().into_inner().out;
+    let lines: Vec<&[u8]> = out
+        .split(|&b| b == b'\n')
+        .filter(|l| !l.is_empty())
+        .collect();
+    assert_eq!(lines.len(), 100);
+    for line in lines {
+        assert!(line == b"AAAAAAAAAA" || line == b"BBBBBBBBBB", "{line:?}");
+    }
+}
+
+#[test]
+fn shared_writer_cancelled_message() {
+    use futures_lite::future;
+
+    let slow = Slow {
+        out: Vec::new(),
+        ready: false,
+    };
+    let writer = SharedWriter::new(AsyncMapWriter::with_capacity(
+        slow,
+        |buf: &mut Vec<u8>| buf.make_ascii_uppercase(),
+        4,
+    ));
+    block_on(async {
+        // Abandon the first message once part of it has been written out
+        let mut message = Box::pin(writer.write_message(b"aaaaaaaaaa\n"));
+        for _ in 0..3 {
+            assert!(future::poll_once(message.as_mut()).await.is_none());
+        }
+        drop(message);
+        writer.write_message(b"bbbb\n").await.unwrap();
+    });
+    let out = writer.into_inner().unwrap().into_inner().out;
+    assert_eq!(out, b"AAAAAAAAAA\nBBBB\n");
+}
+
+#[test]
+fn shared_writer_cancelled_while_waiting() {
+    use futures_lite::future;
+
+    let slow = Slow {
+        out: Vec::new(),
+        ready: false,
+    };
+    let writer = SharedWriter::new(AsyncMapWriter::with_capacity(slow, |_: &mut Vec<u8>| {}, 4));
+    block_on(async {
+        let mut first = Box::pin(writer.write_message(b"first\n"));
+        assert!(future::poll_once(first.as_mut()).await.is_none());
+        // Waits for the first message, then gives up before writing anything
+        let mut second = Box::pin(writer.write_message(b"second\n"));
+        assert!(future::poll_once(second.as_mut()).await.is_none());
+        drop(second);
+        first.await.unwrap();
+        writer.write_message(b"third\n").await.unwrap();
+    });
+    let out = writer.into_inner().unwrap().into_inner().out;
+    assert_eq!(out, b"first\nthird\n");
+}
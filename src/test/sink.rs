@@ -0,0 +1,31 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures_lite::{future::block_on, io::Cursor, AsyncWriteExt};
+
+use crate::InMemoryMapWriter;
+
+#[test]
+fn maps_in_place_in_the_sink() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let seen_clone = Rc::clone(&seen);
+    // Records where each chunk it maps lives
+    let transformer = move |buf: &mut [u8]| {
+        seen_clone.borrow_mut().push(buf.as_ptr() as usize);
+        buf.make_ascii_uppercase();
+    };
+    let mut cursor = Cursor::new(Vec::with_capacity(64));
+    cursor.get_mut().extend_from_slice(b"> ");
+    let mut writer = InMemoryMapWriter::new(cursor, transformer);
+    block_on(async {
+        writer.write_all(b"hello ").await.unwrap();
+        writer.write_all(b"world").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    let sink = writer.get_ref().get_ref();
+    // Each chunk was mapped right where it ended up, without an intermediate copy
+    let start = sink.as_ptr() as usize;
+    assert_eq!(*seen.borrow(), [start + 2, start + 8]);
+    let cursor = writer.into_inner();
+    assert_eq!(cursor.position(), 13);
+    assert_eq!(cursor.into_inner(), b"> HELLO WORLD");
+}
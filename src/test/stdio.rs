@@ -0,0 +1,39 @@
+use std::io::{Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+use futures_lite::{future::block_on, io, AsyncWriteExt};
+
+use crate::{AsyncMapReader, AsyncMapWriter};
+
+/// A blocking writer whose output can still be inspected once it's been moved to the thread pool.
+#[derive(Clone, Default)]
+struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn blocking_filter() {
+    // Stand-ins for stdin and stdout
+    let input = Cursor::new(b"hello from a pipe".to_vec());
+    let output = SharedSink::default();
+
+    let mut reader =
+        AsyncMapReader::from_blocking(input, |buf: &mut [u8]| buf.make_ascii_uppercase());
+    let mut writer = AsyncMapWriter::from_blocking(output.clone(), |buf: &mut Vec<u8>| {
+        buf.retain(|&b| b != b' ')
+    });
+    block_on(async {
+        io::copy(&mut reader, &mut writer).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(*output.0.lock().unwrap(), b"HELLOFROMAPIPE");
+}
@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex};
+
+use futures_lite::{future::block_on, io::Cursor, AsyncWriteExt};
+
+use crate::StreamingMapWriter;
+
+#[test]
+fn expanding_transform_stays_bounded() {
+    const CAPACITY: usize = 8192;
+    const WINDOW: usize = 256;
+
+    let input = (0..64 * 1024).map(|i| i as u8).collect::<Vec<u8>>();
+    let peak = Arc::new(Mutex::new(0));
+    let peak_clone = Arc::clone(&peak);
+    // Repeat every byte 10 times
+    let transformer = move |input: &[u8], sink: &mut Vec<u8>| {
+        for &byte in input {
+            sink.extend_from_slice(&[byte; 10]);
+        }
+        let mut peak = peak_clone.lock().unwrap();
+        *peak = sink.len().max(*peak);
+    };
+
+    let output = Cursor::new(vec![]);
+    let mut writer = StreamingMapWriter::with_window(output, transformer, CAPACITY, WINDOW);
+    block_on(async {
+        writer.write_all(&input).await.unwrap();
+        writer.close().await.unwrap();
+    });
+
+    let result = writer.into_inner().into_inner();
+    let expected = input.iter().flat_map(|&b| [b; 10]).collect::<Vec<u8>>();
+    assert_eq!(result, expected);
+
+    // At most one window of leftover output, plus the output of one window of input,
+    // instead of the 10x expanded output of a whole chunk
+    let peak = *peak.lock().unwrap();
+    assert!(peak <= WINDOW + WINDOW * 10, "Peak output was {peak} bytes");
+}
+
+#[test]
+fn small_writes_are_buffered() {
+    let calls = Arc::new(Mutex::new(0));
+    let calls_clone = Arc::clone(&calls);
+    let transformer = move |input: &[u8], sink: &mut Vec<u8>| {
+        *calls_clone.lock().unwrap() += 1;
+        sink.extend(input.iter().map(u8::to_ascii_uppercase));
+    };
+
+    let mut writer = StreamingMapWriter::new(Cursor::new(vec![]), transformer);
+    block_on(async {
+        writer.write_all(b"abc").await.unwrap();
+        writer.write_all(b"def").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(*calls.lock().unwrap(), 1);
+    assert_eq!(writer.into_inner().into_inner(), b"ABCDEF");
+}
+
+#[test]
+#[should_panic(expected = "capacity must be at least 1")]
+fn zero_capacity() {
+    let copy = |input: &[u8], sink: &mut Vec<u8>| sink.extend_from_slice(input);
+    let _ = StreamingMapWriter::with_window(Cursor::new(vec![]), copy, 0, 4);
+}
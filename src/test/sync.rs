@@ -0,0 +1,29 @@
+use std::io::{BufRead, Read};
+
+use futures_lite::io::Cursor;
+
+use crate::{AsyncMapRead, AsyncMapReader, BlockingBufRead};
+
+#[test]
+fn read_lines_blocking() {
+    let source = Cursor::new(b"first line\nsecond line\n".to_vec());
+    let reader = AsyncMapReader::new(source, |buf: &mut [u8]| buf.make_ascii_uppercase());
+    let mut reader = BlockingBufRead::new(reader);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "FIRST LINE\n");
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "SECOND LINE\n");
+}
+
+#[test]
+fn read_generic_reader_blocking() {
+    let source = Cursor::new(b"unboxed\n".to_vec());
+    let reader = source.map(|buf: &mut [u8]| buf.make_ascii_uppercase());
+    let mut reader = BlockingBufRead::new(reader);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "UNBOXED\n");
+}
@@ -0,0 +1,49 @@
+use std::sync::{Arc, Mutex};
+
+use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+
+use crate::{AsyncMapReader, Verify};
+
+fn reference(buf: &mut [u8]) {
+    buf.iter_mut().for_each(|b| *b = b.to_ascii_uppercase());
+}
+
+#[test]
+fn verify_agreeing() {
+    let fast = |buf: &mut [u8]| buf.make_ascii_uppercase();
+    let mut reader = AsyncMapReader::with_verify(Cursor::new(b"hello".to_vec()), fast, reference);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+    assert_eq!(result, b"HELLO");
+}
+
+#[test]
+fn verify_diverging() {
+    let divergences = Arc::new(Mutex::new(Vec::new()));
+    let divergences_clone = Arc::clone(&divergences);
+    // Forgets to uppercase 'z'
+    let buggy = |buf: &mut [u8]| {
+        buf.iter_mut()
+            .filter(|b| **b != b'z')
+            .for_each(|b| *b = b.to_ascii_uppercase())
+    };
+    let verify = Verify::new(buggy, reference).on_divergence(move |input, candidate, expected| {
+        divergences_clone.lock().unwrap().push((
+            input.to_vec(),
+            candidate.to_vec(),
+            expected.to_vec(),
+        ));
+    });
+    let data = Cursor::new(b"abcdxyz!".to_vec());
+    let mut reader = AsyncMapReader::with_capacity(data, verify, 4);
+    let mut result = Vec::new();
+    block_on(reader.read_to_end(&mut result)).unwrap();
+
+    // The reference output is served
+    assert_eq!(result, b"ABCDXYZ!");
+    // Only the second chunk diverged
+    assert_eq!(
+        *divergences.lock().unwrap(),
+        [(b"xyz!".to_vec(), b"XYz!".to_vec(), b"XYZ!".to_vec())]
+    );
+}
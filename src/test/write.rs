@@ -1,6 +1,12 @@
-use std::sync::atomic::AtomicI32;
+use std::{
+    io,
+    marker::PhantomPinned,
+    pin::Pin,
+    sync::atomic::AtomicI32,
+    task::{Context, Poll},
+};
 
-use futures_lite::{future::block_on, io::Cursor, AsyncWriteExt};
+use futures_lite::{future::block_on, io::Cursor, AsyncWrite, AsyncWriteExt};
 
 use crate::write::AsyncMapWriter;
 
@@ -162,3 +168,838 @@ fn chunk_processing_after_large_write() {
       assert_eq!(result, expected, "Output should match transformed chunks");
   });
 }
+
+pin_project_lite::pin_project! {
+    // A writer that is `!Unpin`, to make sure no `Unpin` bound is required anywhere.
+    struct PinnedWriter {
+        data: Vec<u8>,
+        flushed: bool,
+        closed: bool,
+        #[pin]
+        _pin: PhantomPinned,
+    }
+}
+
+impl PinnedWriter {
+    fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            flushed: false,
+            closed: false,
+            _pin: PhantomPinned,
+        }
+    }
+}
+
+impl AsyncWrite for PinnedWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().data.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        *self.project().flushed = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        *self.project().closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn not_unpin_writer() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = Box::pin(AsyncMapWriter::new(PinnedWriter::new(), transformer));
+
+    block_on(async {
+        writer.write_all(b"pinned").await.unwrap();
+        writer.flush().await.unwrap();
+        {
+            let inner = writer.as_mut().get_pin_mut().project();
+            assert_eq!(inner.data, b"PINNED");
+            assert!(*inner.flushed);
+            assert!(!*inner.closed);
+        }
+
+        writer.write_all(b" data").await.unwrap();
+        writer.close().await.unwrap();
+        let inner = writer.as_mut().get_pin_mut().project();
+        assert_eq!(inner.data, b"PINNED DATA");
+        assert!(*inner.closed);
+    });
+}
+
+#[test]
+fn not_unpin_writer_into_inner() {
+    // `into_inner` takes the wrapper by value, so it works as long as it was never pinned.
+    let writer = AsyncMapWriter::new(PinnedWriter::new(), |_: &mut Vec<u8>| {});
+    let inner = writer.into_inner();
+    assert!(inner.data.is_empty());
+}
+
+#[test]
+fn header_with_empty_payload() {
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+
+    let mut writer = AsyncMapWriter::with_header(output, b"HDR:".to_vec(), transformer);
+    block_on(async {
+        writer.flush().await.unwrap();
+        writer.close().await.unwrap();
+
+        let result = writer.into_inner().into_inner();
+        assert_eq!(result, b"HDR:", "Header should be written exactly once");
+    });
+}
+
+#[test]
+fn header_before_payload() {
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+
+    let mut writer = AsyncMapWriter::with_header(output, b"hdr:".to_vec(), transformer);
+    block_on(async {
+        writer.write_all(b"a").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"bc").await.unwrap();
+        writer.close().await.unwrap();
+
+        // The header itself is not transformed
+        let result = writer.into_inner().into_inner();
+        assert_eq!(result, b"hdr:ABC");
+    });
+}
+
+#[test]
+fn initial_data_is_transformed() {
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+
+    let mut writer = AsyncMapWriter::with_initial_data(output, b"init:".to_vec(), transformer);
+    block_on(async {
+        writer.write_all(b"data").await.unwrap();
+        writer.close().await.unwrap();
+
+        let result = writer.into_inner().into_inner();
+        assert_eq!(result, b"INIT:DATA");
+    });
+}
+
+#[test]
+fn configured_capacity_survives_growth() {
+    let output = Cursor::new(vec![]);
+    // Expand the buffer well past its original capacity
+    let transformer = |buf: &mut Vec<u8>| {
+        let original = buf.clone();
+        for _ in 0..4 {
+            buf.extend_from_slice(&original);
+        }
+    };
+
+    let mut writer = AsyncMapWriter::with_capacity(output, transformer, 4);
+    assert_eq!(writer.configured_capacity(), 4);
+    block_on(async {
+        writer.write_all(b"abc").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(writer.configured_capacity(), 4);
+    assert_eq!(writer.into_inner().into_inner(), b"abcabcabcabcabc");
+}
+
+#[test]
+fn transform_invocation_count() {
+    let output = Cursor::new(vec![]);
+    let mut writer = AsyncMapWriter::with_capacity(output, |_: &mut Vec<u8>| {}, 4);
+    assert_eq!(writer.transform_invocations(), 0);
+
+    block_on(async {
+        writer.write_all(b"ab").await.unwrap();
+        writer.flush().await.unwrap();
+        assert_eq!(writer.transform_invocations(), 1);
+        writer.write_all(b"cdefghij").await.unwrap();
+        writer.flush().await.unwrap();
+        assert_eq!(writer.transform_invocations(), 3);
+        // Closing maps the (empty) final chunk
+        writer.close().await.unwrap();
+        assert_eq!(writer.transform_invocations(), 4);
+    });
+}
+
+#[test]
+fn toggle_transform() {
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::new(output, transformer);
+    writer.set_enabled(false);
+
+    block_on(async {
+        writer.write_all(b"plain ").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.set_enabled(true);
+        writer.write_all(b"mapped").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"plain MAPPED");
+}
+
+#[test]
+fn write_stats() {
+    use crate::MapStats;
+
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::with_capacity(output, transformer, 8).with_stats();
+    block_on(async {
+        writer.write_all(b"abcdef").await.unwrap();
+        writer.flush().await.unwrap();
+        writer.write_all(b"0123456789").await.unwrap();
+        writer.close().await.unwrap();
+    });
+
+    let expected = MapStats {
+        bytes_in: 16,
+        bytes_out: 16,
+        // "abcdef", "01234567", then "89" when closing
+        transform_invocations: 3,
+        inner_calls: 3,
+        max_chunk: 8,
+    };
+    assert_eq!(writer.stats(), expected);
+    assert_eq!(writer.into_inner().into_inner(), b"ABCDEF0123456789");
+}
+
+#[test]
+fn write_padding() {
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| buf.iter_mut().for_each(|b| *b += 1);
+    let mut writer = AsyncMapWriter::with_capacity(output, transformer, 8);
+    block_on(async {
+        writer.write_padding(20, 0).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), [1u8; 20]);
+}
+
+#[test]
+fn write_flush() {
+    let output = Cursor::new(vec![]);
+    let transformer = |buf: &mut Vec<u8>| {
+        buf.insert(0, b'<');
+        buf.push(b'>');
+    };
+    let mut writer = AsyncMapWriter::new(output, transformer);
+    block_on(async {
+        writer.write_flush(b"hello").await.unwrap();
+        let inner = Pin::into_inner(Pin::new(&mut writer).get_pin_mut());
+        assert_eq!(inner.get_ref(), b"<hello>");
+        writer.write_flush(b"world").await.unwrap();
+    });
+    assert_eq!(writer.transform_invocations(), 2);
+    assert_eq!(writer.into_inner().into_inner(), b"<hello><world>");
+}
+
+#[test]
+fn one_inner_write_per_flush() {
+    // Accepts everything it's given, counting the calls
+    #[derive(Default)]
+    struct Counting {
+        data: Vec<u8>,
+        calls: usize,
+    }
+
+    impl AsyncWrite for Counting {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+            self.data.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            bufs: &[io::IoSlice<'_>],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+            bufs.iter().for_each(|buf| self.data.extend_from_slice(buf));
+            Poll::Ready(Ok(bufs.iter().map(|buf| buf.len()).sum()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer =
+        AsyncMapWriter::with_header(Counting::default(), b"hdr:".to_vec(), transformer);
+    block_on(async {
+        for msg in [&b"one"[..], b"two", b"three"] {
+            writer.write_all(msg).await.unwrap();
+            writer.flush().await.unwrap();
+        }
+    });
+    let inner = writer.into_inner();
+    // The header goes out along with the first flush
+    assert_eq!(inner.calls, 3);
+    assert_eq!(inner.data, b"hdr:ONETWOTHREE");
+}
+
+#[test]
+fn map_bytes() {
+    let data = (0..=254).collect::<Vec<u8>>();
+    let mut writer = AsyncMapWriter::map_bytes(Cursor::new(vec![]), |b| b + 1);
+    block_on(async {
+        writer.write_all(&data).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    let expected = data.iter().map(|b| b + 1).collect::<Vec<u8>>();
+    assert_eq!(writer.into_inner().into_inner(), expected);
+}
+
+#[test]
+fn is_closed() {
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), |_: &mut Vec<u8>| {});
+    assert!(!writer.is_closed());
+    block_on(async {
+        writer.write_all(b"data").await.unwrap();
+        writer.flush().await.unwrap();
+        assert!(!writer.is_closed());
+        writer.close().await.unwrap();
+        assert!(writer.is_closed());
+        // Closing again is fine
+        writer.close().await.unwrap();
+    });
+    assert!(writer.is_closed());
+    assert_eq!(writer.into_inner().into_inner(), b"data");
+}
+
+#[test]
+fn flush_and_get_ref() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), transformer);
+    block_on(async {
+        writer.write_all(b"hello world").await.unwrap();
+        let inner = writer.flush_and_get_ref().await.unwrap();
+        assert_eq!(inner.position(), 11);
+        assert_eq!(inner.get_ref(), b"HELLO WORLD");
+    });
+}
+
+#[test]
+fn large_write_vectored_with_buffer() {
+    // Records the slices passed to each call
+    #[derive(Default)]
+    struct Recording {
+        calls: Vec<Vec<Vec<u8>>>,
+    }
+
+    impl AsyncWrite for Recording {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls.push(vec![buf.to_vec()]);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            bufs: &[io::IoSlice<'_>],
+        ) -> Poll<io::Result<usize>> {
+            self.calls
+                .push(bufs.iter().map(|buf| buf.to_vec()).collect());
+            Poll::Ready(Ok(bufs.iter().map(|buf| buf.len()).sum()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::with_capacity(Recording::default(), transformer, 8);
+    block_on(async {
+        writer.write_all(b"abc").await.unwrap();
+        writer.write_all(b"defghijk").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    let calls = writer.into_inner().calls;
+    assert_eq!(calls, [vec![b"ABC".to_vec(), b"DEFGHIJK".to_vec()]]);
+}
+
+#[test]
+fn sequence_numbers() {
+    use crate::MapWriteFn;
+
+    // Prefixes each frame with its sequence number
+    struct Numbered;
+
+    impl MapWriteFn for Numbered {
+        fn map_write(&mut self, _buf: &mut Vec<u8>) {
+            unreachable!("map_write_seq is overridden");
+        }
+
+        fn map_write_seq(&mut self, buf: &mut Vec<u8>, seq: u64) {
+            buf.insert(0, seq as u8);
+        }
+    }
+
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), Numbered);
+    block_on(async {
+        for frame in [b"a", b"b", b"c"] {
+            writer.write_all(frame).await.unwrap();
+            writer.flush().await.unwrap();
+        }
+    });
+    assert_eq!(
+        writer.into_inner().into_inner(),
+        [0, b'a', 1, b'b', 2, b'c']
+    );
+}
+
+#[test]
+fn no_flush_propagation() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let writer = AsyncMapWriter::new(PinnedWriter::new(), transformer).propagate_flush(false);
+    let mut writer = Box::pin(writer);
+
+    block_on(async {
+        writer.write_all(b"data").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    let inner = writer.as_mut().get_pin_mut().project();
+    assert_eq!(inner.data, b"DATA");
+    assert!(!*inner.flushed);
+}
+
+#[test]
+fn rollback_transaction() {
+    let mut writer = AsyncMapWriter::new(Cursor::new(Vec::new()), |buf: &mut Vec<u8>| {
+        buf.make_ascii_uppercase()
+    });
+    block_on(async {
+        writer.write_all(b"keep ").await.unwrap();
+        writer.begin();
+        writer.write_all(b"discard").await.unwrap();
+        assert!(writer.rollback());
+        // There's no transaction left to roll back
+        assert!(!writer.rollback());
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"KEEP ");
+
+    // Committed data is written out, and can't be rolled back any more
+    let mut writer = AsyncMapWriter::new(Cursor::new(Vec::new()), |_: &mut Vec<u8>| {});
+    block_on(async {
+        writer.begin();
+        writer.write_all(b"data").await.unwrap();
+        writer.commit().await.unwrap();
+        assert!(!writer.rollback());
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"data");
+}
+
+#[test]
+fn fallible_mapping() {
+    use crate::TryMapWriteFn;
+
+    // Only lets ASCII data through
+    struct Strict;
+
+    impl TryMapWriteFn for Strict {
+        fn try_map_write(&mut self, buf: &mut Vec<u8>) -> std::io::Result<()> {
+            if !buf.is_ascii() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "non-ASCII data",
+                ));
+            }
+            buf.make_ascii_uppercase();
+            Ok(())
+        }
+    }
+
+    let mut writer = AsyncMapWriter::try_new(Cursor::new(Vec::new()), Strict);
+    block_on(async {
+        writer.write_all("ok é".as_bytes()).await.unwrap();
+        let err = writer.flush().await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // The data is still buffered untransformed, so the error is reported again
+        let err = writer.close().await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    });
+    assert!(writer.into_inner().into_inner().is_empty());
+
+    // Infallible closures still work
+    let mut writer =
+        AsyncMapWriter::try_new(Cursor::new(Vec::new()), |buf: &mut Vec<u8>| buf.reverse());
+    block_on(async {
+        writer.write_all(b"abc").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"cba");
+}
+
+#[test]
+fn inner_accessors() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::new(Cursor::new(Vec::new()), transformer);
+    block_on(async {
+        writer.write_all(b"mapped ").await.unwrap();
+        // Nothing reached the underlying writer yet
+        assert!(writer.get_ref().get_ref().is_empty());
+        writer.flush().await.unwrap();
+        writer.get_mut().write_all(b"raw").await.unwrap();
+    });
+    assert_eq!(writer.get_ref().get_ref(), b"MAPPED raw");
+}
+
+#[test]
+fn min_transform_size() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let chunks = Rc::new(RefCell::new(Vec::new()));
+    let seen = Rc::clone(&chunks);
+    let transformer = move |buf: &mut Vec<u8>| seen.borrow_mut().push(buf.len());
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(Vec::new()), transformer, 16)
+        .with_min_transform_size(64);
+    block_on(async {
+        for _ in 0..8 {
+            writer.write_all(&[b'a'; 8]).await.unwrap();
+        }
+        // 64 bytes are buffered, but the transform hasn't been called yet
+        assert!(chunks.borrow().is_empty());
+        writer.write_all(&[b'b'; 8]).await.unwrap();
+        assert_eq!(*chunks.borrow(), [64]);
+        // Flushing maps whatever is buffered
+        writer.flush().await.unwrap();
+        assert_eq!(*chunks.borrow(), [64, 8]);
+    });
+    assert_eq!(writer.into_inner().into_inner().len(), 72);
+}
+
+#[test]
+fn seek_flushes_first() {
+    use futures_lite::{io::SeekFrom, AsyncSeekExt};
+
+    // Tags every chunk, so chunk boundaries are visible in the output
+    let transformer = |buf: &mut Vec<u8>| buf.insert(0, b'#');
+    let mut writer = AsyncMapWriter::new(Cursor::new(Vec::new()), transformer);
+    block_on(async {
+        writer.write_all(b"abcdef").await.unwrap();
+        assert_eq!(writer.seek(SeekFrom::Start(2)).await.unwrap(), 2);
+        writer.write_all(b"XY").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"#a#XYef");
+}
+
+#[test]
+fn conditional_header() {
+    let bom = "\u{feff}".as_bytes().to_vec();
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+
+    let mut writer =
+        AsyncMapWriter::with_conditional_header(Cursor::new(vec![]), bom.clone(), transformer);
+    block_on(async {
+        writer.flush().await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert!(writer.into_inner().into_inner().is_empty());
+
+    let mut writer =
+        AsyncMapWriter::with_conditional_header(Cursor::new(vec![]), bom.clone(), transformer);
+    block_on(async {
+        writer.flush().await.unwrap();
+        writer.write_all(b"text").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(
+        writer.into_inner().into_inner(),
+        [&bom[..], b"TEXT"].concat()
+    );
+}
+
+#[test]
+fn unboxed_mapping_function() {
+    use crate::write::AsyncMapWrite;
+
+    fn exclaim(buf: &mut Vec<u8>) {
+        buf.push(b'!')
+    }
+
+    let mut writer: AsyncMapWriter<'_, _, fn(&mut Vec<u8>)> = Cursor::new(vec![]).map(exclaim);
+    block_on(writer.write_all(b"hello")).unwrap();
+    block_on(writer.flush()).unwrap();
+    assert_eq!(writer.into_inner().into_inner(), b"hello!");
+
+    let mut boxed: crate::BoxedAsyncMapWriter<'_, _> =
+        AsyncMapWriter::from_fn(Cursor::new(vec![]), Box::new(exclaim));
+    block_on(boxed.write_all(b"hello")).unwrap();
+    block_on(boxed.flush()).unwrap();
+    assert_eq!(boxed.into_inner().into_inner(), b"hello!");
+}
+
+#[test]
+fn into_inner_flushed_writes_buffered_data() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), transformer);
+    block_on(writer.write_all(b"hello world")).unwrap();
+    assert!(writer.get_ref().get_ref().is_empty());
+    let inner = block_on(writer.into_inner_flushed()).unwrap();
+    assert_eq!(inner.into_inner(), b"HELLO WORLD");
+}
+
+#[test]
+fn pending_len_detects_unflushed_data() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), transformer);
+    assert_eq!(writer.pending_len(), 0);
+    block_on(writer.write_all(b"data")).unwrap();
+    assert_eq!(writer.pending_len(), 4);
+    block_on(writer.flush()).unwrap();
+    assert_eq!(writer.pending_len(), 0);
+    assert_eq!(writer.into_inner().into_inner(), b"DATA");
+
+    // Without flushing, the buffered data never reaches the underlying writer
+    let mut writer = AsyncMapWriter::new(Cursor::new(vec![]), transformer);
+    block_on(writer.write_all(b"data")).unwrap();
+    assert_eq!(writer.pending_len(), 4);
+    assert!(writer.into_inner().into_inner().is_empty());
+}
+
+#[test]
+fn debug_output() {
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), |_: &mut Vec<u8>| {}, 64);
+    block_on(writer.write_all(b"abc")).unwrap();
+    let debug = format!("{writer:?}");
+    assert!(debug.starts_with("AsyncMapWriter"), "{debug}");
+    assert!(debug.contains("capacity: 64"), "{debug}");
+    assert!(debug.contains("buffered: 3"), "{debug}");
+}
+
+#[test]
+fn capacity_and_buffered() {
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), |_: &mut Vec<u8>| {}, 32);
+    assert!(writer.capacity() >= 32);
+    assert_eq!(writer.buffered(), 0);
+    block_on(writer.write_all(b"hello")).unwrap();
+    assert_eq!(writer.buffered(), 5);
+    block_on(writer.flush()).unwrap();
+    assert_eq!(writer.buffered(), 0);
+}
+
+#[test]
+#[should_panic(expected = "capacity must be at least 1")]
+fn zero_capacity() {
+    let _ = AsyncMapWriter::with_capacity(Cursor::new(vec![]), |_: &mut Vec<u8>| {}, 0);
+}
+
+#[test]
+fn capacity_of_one() {
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), transformer, 1);
+    block_on(async {
+        writer.write_all(b"abc").await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"ABC");
+}
+
+#[test]
+fn write_vectored_buffers_all_slices() {
+    // Accepts everything it's given, counting the calls
+    #[derive(Default)]
+    struct Counting {
+        data: Vec<u8>,
+        calls: usize,
+    }
+
+    impl AsyncWrite for Counting {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+            self.data.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    let transformer = |buf: &mut Vec<u8>| buf.make_ascii_uppercase();
+    let mut writer = AsyncMapWriter::with_capacity(Counting::default(), transformer, 16);
+    block_on(async {
+        let bufs = [
+            io::IoSlice::new(b"one "),
+            io::IoSlice::new(b""),
+            io::IoSlice::new(b"two "),
+            io::IoSlice::new(b"three"),
+        ];
+        assert_eq!(writer.write_vectored(&bufs).await.unwrap(), 13);
+        // Too large to buffer, only the first slice is written
+        let bufs = [io::IoSlice::new(b" four"), io::IoSlice::new(b" five")];
+        assert_eq!(writer.write_vectored(&bufs).await.unwrap(), 5);
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(writer.transform_invocations(), 2);
+    let inner = writer.into_inner();
+    assert_eq!(inner.data, b"ONE TWO THREE FOUR");
+    assert_eq!(inner.calls, 2);
+}
+
+#[test]
+fn adaptive_buffer() {
+    use std::{cell::Cell, rc::Rc};
+
+    let grown = Rc::new(Cell::new(0));
+    let grown_clone = Rc::clone(&grown);
+    // Doubles chunks containing a '!', leaves the others alone
+    let transformer = move |buf: &mut Vec<u8>| {
+        if buf.contains(&b'!') {
+            *buf = buf.iter().flat_map(|&b| [b, b]).collect();
+            grown_clone.set(grown_clone.get().max(buf.capacity()));
+        }
+    };
+    let mut writer = AsyncMapWriter::with_adaptive_buffer(Cursor::new(vec![]), transformer, 8, 12);
+    block_on(async {
+        writer.write_all(b"abcdefgh").await.unwrap();
+        writer.flush().await.unwrap();
+        assert_eq!(writer.capacity(), 8);
+
+        // The burst grows the buffer within the cap, then it shrinks back
+        writer.write_all(b"ab!cd").await.unwrap();
+        writer.flush().await.unwrap();
+        assert!((10..=12).contains(&grown.get()), "{}", grown.get());
+        assert_eq!(writer.capacity(), 8);
+
+        // Past the cap, the chunk is rejected
+        writer.write_all(b"a!cdefg").await.unwrap();
+        let err = writer.flush().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        writer.write_all(b"xyz").await.unwrap();
+        writer.flush().await.unwrap();
+    });
+    assert_eq!(writer.into_inner().into_inner(), b"abcdefghaabb!!ccddxyz");
+}
+
+#[test]
+fn one_byte_at_a_time_sink() {
+    // Accepts a single byte per call
+    #[derive(Default)]
+    struct Trickle {
+        data: Vec<u8>,
+        calls: usize,
+    }
+
+    impl AsyncWrite for Trickle {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+            self.data.extend_from_slice(&buf[..1]);
+            Poll::Ready(Ok(1))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    let data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+    let transformer = |buf: &mut Vec<u8>| buf.iter_mut().for_each(|b| *b = b.wrapping_add(1));
+    let mut writer = AsyncMapWriter::with_capacity(Trickle::default(), transformer, 1024);
+    block_on(async {
+        // Buffered data followed by a large write, which gets staged
+        writer.write_all(&data[..10]).await.unwrap();
+        writer.write_all(&data[10..]).await.unwrap();
+        writer.close().await.unwrap();
+    });
+    assert_eq!(writer.pending_len(), 0);
+    let inner = writer.into_inner();
+    let expected: Vec<u8> = data.iter().map(|b| b.wrapping_add(1)).collect();
+    assert_eq!(inner.data, expected);
+    assert_eq!(inner.calls, 4096);
+}
+
+#[test]
+fn last_error() {
+    use crate::TryMapWriteFn;
+
+    struct Rejecting;
+
+    impl TryMapWriteFn for Rejecting {
+        fn try_map_write(&mut self, _buf: &mut Vec<u8>) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "rejected"))
+        }
+    }
+
+    let mut writer = AsyncMapWriter::try_new(Cursor::new(vec![]), Rejecting);
+    assert!(writer.last_error().is_none());
+    block_on(writer.write_all(b"data")).unwrap();
+    let err = block_on(writer.flush()).unwrap_err();
+    let last = writer.last_error().unwrap();
+    assert_eq!(last.kind(), err.kind());
+    assert_eq!(last.to_string(), "rejected");
+}
+
+#[test]
+fn progress_counts_mapped_bytes() {
+    let doubling = |buf: &mut Vec<u8>| {
+        *buf = buf.iter().flat_map(|&b| [b, b]).collect();
+    };
+    let mut reports = Vec::new();
+    let mut writer = AsyncMapWriter::with_capacity(Cursor::new(vec![]), doubling, 8)
+        .on_progress(|n| reports.push(n));
+    block_on(async {
+        writer
+            .write_all(b"hello world, from the writer")
+            .await
+            .unwrap();
+        writer.close().await.unwrap();
+    });
+    let output = writer.into_inner().into_inner();
+    assert_eq!(output.len(), 56);
+    assert!(reports.len() > 1, "{reports:?}");
+    assert_eq!(reports.iter().sum::<usize>(), output.len());
+}
@@ -0,0 +1,74 @@
+use futures_lite::AsyncRead;
+
+use crate::{AsyncMapReader, MapReadFn};
+
+type DivergenceFn<'a> = Box<dyn FnMut(&[u8], &[u8], &[u8]) + 'a>;
+
+/// A read mapping function running two mapping functions on each chunk and comparing their outputs.
+///
+/// This is meant for checking a new (e.g. optimized) mapping function against a reference one:
+/// both are applied to their own copy of every chunk, and the output of the reference is served.
+/// When the outputs differ, the divergence callback is called if one was set with
+/// [`Verify::on_divergence`]; otherwise this panics in debug builds.
+pub struct Verify<'a, F, G> {
+    candidate: F,
+    reference: G,
+    on_divergence: Option<DivergenceFn<'a>>,
+}
+
+impl<'a, F: MapReadFn, G: MapReadFn> Verify<'a, F, G> {
+    /// Creates a new `Verify` checking `candidate` against `reference`.
+    pub fn new(candidate: F, reference: G) -> Self {
+        Self {
+            candidate,
+            reference,
+            on_divergence: None,
+        }
+    }
+
+    /// Sets a callback to report divergences, instead of panicking in debug builds.
+    ///
+    /// The callback receives the chunk before mapping, the output of the candidate,
+    /// and the output of the reference.
+    pub fn on_divergence(mut self, callback: impl FnMut(&[u8], &[u8], &[u8]) + 'a) -> Self {
+        self.on_divergence = Some(Box::new(callback));
+        self
+    }
+}
+
+impl<F: MapReadFn, G: MapReadFn> MapReadFn for Verify<'_, F, G> {
+    fn map_read(&mut self, buf: &mut [u8]) {
+        let input = buf.to_vec();
+        let mut candidate = input.clone();
+        self.candidate.map_read(&mut candidate);
+        self.reference.map_read(buf);
+        if candidate != buf {
+            match &mut self.on_divergence {
+                Some(callback) => callback(&input, &candidate, buf),
+                None => debug_assert_eq!(candidate, buf, "mapping functions diverged"),
+            }
+        }
+    }
+
+    fn init(&mut self, first_chunk: &[u8]) {
+        self.candidate.init(first_chunk);
+        self.reference.init(first_chunk);
+    }
+
+    fn finalize(&mut self, out: &mut Vec<u8>) {
+        self.reference.finalize(out);
+    }
+}
+
+impl<'a, R: AsyncRead> AsyncMapReader<'a, R> {
+    /// Create a new wrapper checking `candidate` against `reference` on every chunk, see [`Verify`].
+    ///
+    /// The output of `reference` is served, and divergences panic in debug builds.
+    pub fn with_verify(
+        reader: R,
+        candidate: impl MapReadFn + 'a,
+        reference: impl MapReadFn + 'a,
+    ) -> Self {
+        Self::new(reader, Verify::new(candidate, reference))
+    }
+}
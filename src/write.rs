@@ -1,14 +1,17 @@
 use std::{
+    fmt,
+    io::IoSlice,
+    marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
 };
 
 use futures_lite::{
     io::{self, Result},
-    ready, AsyncWrite,
+    ready, AsyncSeek, AsyncWrite, AsyncWriteExt,
 };
 
-use crate::DEFAULT_BUFFER_SIZE;
+use crate::{read::copy_error, MapStats, DEFAULT_BUFFER_SIZE};
 
 /// A trait for mapping data written to an underlying writer.
 pub trait MapWriteFn {
@@ -21,6 +24,27 @@ pub trait MapWriteFn {
     /// This behavior is intended to allow for a variety of use cases, such as base64 encoding,
     /// which may require expanding the buffer size to accommodate the transformed data.
     fn map_write(&mut self, buf: &mut Vec<u8>);
+
+    /// Applies the mapping function to a chunk, along with its sequence number.
+    ///
+    /// The sequence number starts at 0 and is incremented for every chunk passed to the mapping function,
+    /// which allows numbering frames without keeping track of them separately.
+    /// The default implementation ignores the sequence number and calls [`MapWriteFn::map_write`].
+    fn map_write_seq(&mut self, buf: &mut Vec<u8>, seq: u64) {
+        let _ = seq;
+        self.map_write(buf);
+    }
+
+    /// Applies the mapping function to the final chunk of data, when the writer is closed.
+    ///
+    /// Unlike [`MapWriteFn::map_write`], this is called exactly once, even if the final chunk is empty.
+    /// This allows mapping functions to pad or terminate the stream.
+    /// The default implementation calls [`MapWriteFn::map_write`] if the buffer is not empty.
+    fn map_write_final(&mut self, buf: &mut Vec<u8>) {
+        if !buf.is_empty() {
+            self.map_write(buf);
+        }
+    }
 }
 
 impl<F> MapWriteFn for F
@@ -32,6 +56,64 @@ where
     }
 }
 
+/// A trait for mapping data written to an underlying writer, where the mapping can fail.
+///
+/// Every [`MapWriteFn`] is also a `TryMapWriteFn` that never fails,
+/// so the infallible mapping functions keep working with [`AsyncMapWriter::try_new`].
+pub trait TryMapWriteFn {
+    /// Applies a fallible mapping function to the data before writing it to the underlying writer.
+    ///
+    /// If this fails, the error is returned by the write, flush or close call that triggered the mapping,
+    /// and the data stays in the buffer untransformed, so the mapping is attempted again on the next call.
+    /// The mapping function should therefore leave `buf` unchanged when it fails.
+    fn try_map_write(&mut self, buf: &mut Vec<u8>) -> io::Result<()>;
+
+    /// See [`MapWriteFn::map_write_seq`].
+    fn try_map_write_seq(&mut self, buf: &mut Vec<u8>, seq: u64) -> io::Result<()> {
+        let _ = seq;
+        self.try_map_write(buf)
+    }
+
+    /// See [`MapWriteFn::map_write_final`].
+    fn try_map_write_final(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if !buf.is_empty() {
+            self.try_map_write(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryMapWriteFn for Box<dyn TryMapWriteFn + '_> {
+    fn try_map_write(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        (**self).try_map_write(buf)
+    }
+
+    fn try_map_write_seq(&mut self, buf: &mut Vec<u8>, seq: u64) -> io::Result<()> {
+        (**self).try_map_write_seq(buf, seq)
+    }
+
+    fn try_map_write_final(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        (**self).try_map_write_final(buf)
+    }
+}
+
+impl<T: MapWriteFn> TryMapWriteFn for T {
+    fn try_map_write(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        self.map_write(buf);
+        Ok(())
+    }
+
+    fn try_map_write_seq(&mut self, buf: &mut Vec<u8>, seq: u64) -> io::Result<()> {
+        self.map_write_seq(buf, seq);
+        Ok(())
+    }
+
+    fn try_map_write_final(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        self.map_write_final(buf);
+        Ok(())
+    }
+}
+
 pin_project_lite::pin_project! {
   /// A wrapper around an `AsyncWrite` that allows for data processing
   /// before the actual I/O operation.
@@ -43,16 +125,39 @@ pin_project_lite::pin_project! {
   /// The buffer size also acts as a threshold for the length of data passed to the mapping function, 
   /// and will be gauranteed to be equal to or less than the specified capacity, unless the 
   /// function modifies the buffer capacity itself.
-  pub struct AsyncMapWriter<'a, W> {
+  ///
+  /// The mapping function is boxed by default, see [`AsyncMapWriter::from_fn`] and [`AsyncMapWrite::map`]
+  /// to call it without the allocation and dynamic dispatch.
+  pub struct AsyncMapWriter<'a, W, F = Box<dyn TryMapWriteFn + 'a>> {
      #[pin]
      inner: W,
-     process_fn: Box<dyn MapWriteFn + 'a>,
+     process_fn: F,
      buf: Vec<u8>, // Buffer to hold data before writing
      written: usize, // Track how much has been written to the buffer
      transformed: bool, // Add a flag to track if the buffer is already transformed
+     header: Vec<u8>, // Untransformed bytes still to be written ahead of any data
+     staged: Vec<u8>, // Mapped data from a large write, to be written right after the buffer
+     staged_written: usize, // How much of the staged data has been written
+     capacity: usize, // Capacity requested at construction
+     finished: bool, // Whether the final chunk has been mapped
+     closed: bool, // Whether the underlying writer has been closed
+     propagate_flush: bool, // Whether flushing also flushes the underlying writer
+     invocations: u64, // Number of times the mapping function has been called
+     enabled: bool, // Whether the mapping function is applied
+     stats: Option<MapStats>, // Statistics, if enabled
+     checkpoint: Option<usize>, // Buffer length when the current transaction began, while it can be rolled back
+     min_transform_size: usize, // Amount of data to buffer before mapping it, unless flushing
+     conditional_header: bool, // Whether the header is only written along with some data
+     max_capacity: Option<usize>, // Length the mapping function may grow the buffer to, if the buffer is adaptive
+     last_error: Option<io::Error>, // Copy of the last error returned
+     progress: Option<Box<dyn FnMut(usize) + Send + 'a>>, // Called with the length of every write to the underlying writer
+     lifetime: PhantomData<&'a ()>, // Lifetime of the default boxed mapping function
   }
 }
 
+/// An [`AsyncMapWriter`] with a boxed mapping function, as created by its constructors.
+pub type BoxedAsyncMapWriter<'a, W> = AsyncMapWriter<'a, W, Box<dyn TryMapWriteFn + 'a>>;
+
 impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
     /// Creates a new `AsyncMapWriter` with a default buffer size of 8KB.
     /// 
@@ -64,22 +169,313 @@ impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
     /// Creates a new `AsyncMapWriter` with a specified buffer capacity.
     /// 
     /// This function initializes the writer with the provided `process_fn` to map the data before writing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
     pub fn with_capacity(writer: W, process_fn: impl MapWriteFn + 'a, capacity: usize) -> Self {
+        Self::try_with_capacity(writer, process_fn, capacity)
+    }
+
+    /// Creates a new `AsyncMapWriter` with a fallible mapping function and a default buffer size of 8KB.
+    ///
+    /// Errors from the mapping function are returned by the call that triggered them,
+    /// see [`TryMapWriteFn::try_map_write`].
+    pub fn try_new(writer: W, process_fn: impl TryMapWriteFn + 'a) -> Self {
+        Self::try_with_capacity(writer, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates a new `AsyncMapWriter` with a fallible mapping function and a specified buffer capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn try_with_capacity(
+        writer: W,
+        process_fn: impl TryMapWriteFn + 'a,
+        capacity: usize,
+    ) -> Self {
+        Self::from_fn_with_capacity(writer, Box::new(process_fn), capacity)
+    }
+
+    /// Creates a new `AsyncMapWriter` applying `f` to every byte written.
+    pub fn map_bytes(writer: W, mut f: impl FnMut(u8) -> u8 + 'a) -> Self {
+        Self::new(writer, move |buf: &mut Vec<u8>| {
+            buf.iter_mut().for_each(|b| *b = f(*b))
+        })
+    }
+
+    /// Creates a new `AsyncMapWriter` that writes `header` to the underlying writer before any other data.
+    ///
+    /// The header is written as-is, without being passed to the mapping function, on the first flush.
+    /// It is written exactly once, even if no data is written before the writer is closed.
+    pub fn with_header(writer: W, header: Vec<u8>, process_fn: impl MapWriteFn + 'a) -> Self {
+        let mut this = Self::new(writer, process_fn);
+        this.header = header;
+        this
+    }
+
+    /// Creates a new `AsyncMapWriter` that writes `header` to the underlying writer right before the first mapped data.
+    ///
+    /// Unlike [`AsyncMapWriter::with_header`], the header is omitted entirely if no data is written,
+    /// or if the mapping function never produces any output, e.g. for a byte order mark on possibly empty text.
+    pub fn with_conditional_header(
+        writer: W,
+        header: Vec<u8>,
+        process_fn: impl MapWriteFn + 'a,
+    ) -> Self {
+        Self {
+            conditional_header: true,
+            ..Self::with_header(writer, header, process_fn)
+        }
+    }
+
+    /// Creates a new `AsyncMapWriter` whose buffer starts at `initial` bytes, and may be grown
+    /// by the mapping function up to `max` bytes for chunks it expands.
+    ///
+    /// Once an expanded chunk is written out, the buffer shrinks back to `initial` bytes,
+    /// so occasional bursts don't keep the memory allocated.
+    /// Mapping a chunk to more than `max` bytes fails with an [`io::ErrorKind::InvalidData`] error,
+    /// and the chunk is discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is zero, or larger than `max`.
+    pub fn with_adaptive_buffer(
+        writer: W,
+        process_fn: impl MapWriteFn + 'a,
+        initial: usize,
+        max: usize,
+    ) -> Self {
+        assert!(
+            initial <= max,
+            "maximum capacity must not be less than the initial capacity"
+        );
+        Self {
+            max_capacity: Some(max),
+            ..Self::with_capacity(writer, process_fn, initial)
+        }
+    }
+
+    /// Creates a new `AsyncMapWriter` whose buffer is pre-filled with `data`.
+    ///
+    /// Unlike [`AsyncMapWriter::with_header`], the initial data goes through the mapping function
+    /// together with the first chunk of written data.
+    /// If `data` is larger than the default capacity, the buffer is grown to hold it.
+    pub fn with_initial_data(writer: W, data: Vec<u8>, process_fn: impl MapWriteFn + 'a) -> Self {
+        let mut this = Self::with_capacity(writer, process_fn, DEFAULT_BUFFER_SIZE.max(data.len()));
+        this.buf.extend_from_slice(&data);
+        this
+    }
+}
+
+impl<'a, W: AsyncWrite, F: TryMapWriteFn> AsyncMapWriter<'a, W, F> {
+    /// Creates a new `AsyncMapWriter` calling `process_fn` directly, without boxing it.
+    ///
+    /// This avoids an allocation and dynamic dispatch for every chunk, at the cost of
+    /// naming the type of the mapping function in the type of the writer, see [`BoxedAsyncMapWriter`].
+    pub fn from_fn(writer: W, process_fn: F) -> Self {
+        Self::from_fn_with_capacity(writer, process_fn, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates a new `AsyncMapWriter` calling `process_fn` directly, with a specified buffer capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn from_fn_with_capacity(writer: W, process_fn: F, capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be at least 1");
         Self {
             inner: writer,
-            process_fn: Box::new(process_fn),
+            process_fn,
             buf: Vec::with_capacity(capacity),
             written: 0,
             transformed: false,
+            header: Vec::new(),
+            staged: Vec::new(),
+            staged_written: 0,
+            capacity,
+            finished: false,
+            closed: false,
+            propagate_flush: true,
+            invocations: 0,
+            enabled: true,
+            stats: None,
+            checkpoint: None,
+            min_transform_size: 0,
+            conditional_header: false,
+            max_capacity: None,
+            last_error: None,
+            progress: None,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Returns a copy of the last error returned by this writer, if any.
+    ///
+    /// The copy keeps the kind and message of the error, but not its source.
+    /// This lets a supervisor inspect a failure after the error itself was handled elsewhere.
+    pub fn last_error(&self) -> Option<&io::Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Call `progress` after every successful write to the underlying writer,
+    /// with the number of bytes it accepted.
+    ///
+    /// This counts the data as it reaches the underlying writer, header included,
+    /// so a mapping function expanding the data is reflected, e.g. to drive an upload progress bar.
+    /// The callback must be `Send`, so the writer can still be moved to another thread.
+    pub fn on_progress(mut self, progress: impl FnMut(usize) + Send + 'a) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Returns the current capacity of the internal buffer.
+    ///
+    /// This can grow past [`AsyncMapWriter::configured_capacity`] if the mapping function grows the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Returns the number of bytes in the internal buffer, including mapped bytes partially written out.
+    ///
+    /// See [`AsyncMapWriter::pending_len`] for only the bytes still to be written.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the number of buffered bytes that haven't reached the underlying writer yet.
+    ///
+    /// This counts mapped bytes for data that already went through the mapping function.
+    /// If it's zero, [`AsyncMapWriter::into_inner`] doesn't lose any written data.
+    pub fn pending_len(&self) -> usize {
+        self.buf.len() - self.written + self.staged.len() - self.staged_written
+    }
+
+    /// Returns the buffer capacity this writer was constructed with.
+    ///
+    /// This stays the same even if the mapping function grows the internal buffer.
+    pub fn configured_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of times the mapping function has been called.
+    ///
+    /// The call to [`MapWriteFn::map_write_final`] made when closing the writer is included.
+    pub fn transform_invocations(&self) -> u64 {
+        self.invocations
+    }
+
+    /// Start collecting statistics about this writer, see [`AsyncMapWriter::stats`].
+    pub fn with_stats(mut self) -> Self {
+        self.stats = Some(MapStats::default());
+        self
+    }
+
+    /// Returns the statistics collected since [`AsyncMapWriter::with_stats`] was called.
+    ///
+    /// If statistics aren't enabled, only [`MapStats::transform_invocations`] is filled in.
+    pub fn stats(&self) -> MapStats {
+        MapStats {
+            transform_invocations: self.invocations,
+            ..self.stats.unwrap_or_default()
+        }
+    }
+
+    /// Enables or disables the mapping function.
+    ///
+    /// While disabled, data is still buffered but written out unchanged.
+    /// The setting is checked when a chunk is mapped, so it applies to any data still in the buffer,
+    /// while data that was already transformed stays transformed.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns `true` if the mapping function is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Only call the mapping function once at least `n` bytes are buffered, except when flushing or closing.
+    ///
+    /// Small writes are accumulated (growing the buffer if needed) until there's enough data,
+    /// which avoids mapping tiny chunks with mapping functions that have a per-call overhead.
+    pub fn with_min_transform_size(mut self, n: usize) -> Self {
+        self.min_transform_size = n;
+        self.buf.reserve(n.saturating_sub(self.buf.len()));
+        self
+    }
+
+    /// Sets whether flushing this writer also flushes the underlying writer (the default).
+    ///
+    /// When disabled, flushing still maps the buffered data and writes it to the underlying writer,
+    /// but leaves flushing the underlying writer itself to the caller.
+    pub fn propagate_flush(mut self, propagate: bool) -> Self {
+        self.propagate_flush = propagate;
+        self
+    }
+
+    /// Returns `true` once the writer has been closed successfully.
+    ///
+    /// Closing again afterwards is a no-op.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Starts a transaction: data written from now on can be discarded with [`AsyncMapWriter::rollback`].
+    ///
+    /// Only data still in the buffer can be rolled back, so transactions should fit within the buffer capacity.
+    /// Starting a transaction again moves the checkpoint to the current position.
+    pub fn begin(&mut self) {
+        self.checkpoint = Some(self.buf.len());
+    }
+
+    /// Discards the data written since [`AsyncMapWriter::begin`], and ends the transaction.
+    ///
+    /// Returns `false` (discarding nothing) if there's no transaction, or if some of its data
+    /// has already been passed to the mapping function, e.g. because the buffer filled up.
+    pub fn rollback(&mut self) -> bool {
+        match self.checkpoint.take() {
+            Some(checkpoint) if !self.transformed => {
+                if let Some(stats) = &mut self.stats {
+                    let discarded = (self.buf.len() - checkpoint) as u64;
+                    stats.bytes_in = stats.bytes_in.saturating_sub(discarded);
+                }
+                self.buf.truncate(checkpoint);
+                true
+            }
+            _ => false,
         }
     }
 
     /// Consumes the `AsyncMapWriter` and returns the underlying writer.
+    ///
+    /// **Any buffered data that wasn't flushed yet is dropped without being mapped or written.**
+    /// Use [`AsyncMapWriter::into_inner_flushed`] to write it out first,
+    /// or check [`AsyncMapWriter::pending_len`] to detect it.
     pub fn into_inner(self) -> W {
         self.inner
     }
 
-    fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut W> {
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// Writing to the underlying writer directly bypasses the buffer and the mapping function,
+    /// so flush this writer first, or the data may end up interleaved with buffered data.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Gets a pinned mutable reference to the underlying writer.
+    ///
+    /// This does not require `W: Unpin`, so it can be used to reach a `!Unpin`
+    /// writer while the `AsyncMapWriter` itself is pinned.
+    /// The same caveat as [`AsyncMapWriter::get_mut`] applies.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut W> {
         self.project().inner
     }
 
@@ -89,24 +485,93 @@ impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
         let mut this = self.project();
         // If nothing has been written yet and the buffer isn't transformed, apply the transformation
         if *this.written == 0 && !this.buf.is_empty() && !*this.transformed {
-            (this.process_fn).map_write(this.buf);
+            if *this.enabled {
+                let len = this.buf.len();
+                (this.process_fn).try_map_write_seq(this.buf, *this.invocations)?;
+                check_growth(this.buf, *this.max_capacity)?;
+                if let Some(stats) = this.stats {
+                    stats.record_chunk(len);
+                }
+                *this.invocations += 1;
+            }
             *this.transformed = true; // Mark as transformed
+            *this.checkpoint = None;
+        }
+        // Write out any pending header before the buffered data,
+        // along with the buffered data itself if the underlying writer supports vectored writes
+        let has_data = *this.written < this.buf.len() || *this.staged_written < this.staged.len();
+        while !this.header.is_empty() && (has_data || !*this.conditional_header) {
+            let res = if *this.written < this.buf.len() {
+                let bufs = [
+                    IoSlice::new(this.header),
+                    IoSlice::new(&this.buf[*this.written..]),
+                ];
+                this.inner.as_mut().poll_write_vectored(cx, &bufs)
+            } else {
+                this.inner.as_mut().poll_write(cx, this.header)
+            };
+            match res {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "write zero",
+                    )));
+                }
+                Poll::Ready(Ok(n)) => {
+                    let header_written = std::cmp::min(n, this.header.len());
+                    this.header.drain(..header_written);
+                    *this.written += n - header_written;
+                    if let Some(stats) = this.stats {
+                        stats.bytes_out += n as u64;
+                        stats.inner_calls += 1;
+                    }
+                    if let Some(progress) = this.progress {
+                        progress(n);
+                    }
+                }
+                Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
         }
         let len = this.buf.len();
         let mut ret = Ok(());
 
-        while *this.written < len {
-            match this
-                .inner
-                .as_mut()
-                .poll_write(cx, &this.buf[*this.written..])
-            {
+        // Only offsets are advanced while writing, the written data is dropped once at the end,
+        // so a slow underlying writer accepting a few bytes at a time doesn't cost a move of the rest every time
+        while *this.written < len || *this.staged_written < this.staged.len() {
+            // Write the buffered and staged data together when there's both
+            let res = if *this.written < len && *this.staged_written < this.staged.len() {
+                let bufs = [
+                    IoSlice::new(&this.buf[*this.written..]),
+                    IoSlice::new(&this.staged[*this.staged_written..]),
+                ];
+                this.inner.as_mut().poll_write_vectored(cx, &bufs)
+            } else if *this.written < len {
+                this.inner
+                    .as_mut()
+                    .poll_write(cx, &this.buf[*this.written..])
+            } else {
+                this.inner
+                    .as_mut()
+                    .poll_write(cx, &this.staged[*this.staged_written..])
+            };
+            match res {
                 Poll::Ready(Ok(0)) => {
                     ret = Err(io::Error::new(io::ErrorKind::WriteZero, "write zero"));
                     break;
                 }
                 Poll::Ready(Ok(n)) => {
-                    *this.written += n;
+                    let buffered = std::cmp::min(n, len - *this.written);
+                    *this.written += buffered;
+                    *this.staged_written += n - buffered;
+                    if let Some(stats) = this.stats {
+                        stats.bytes_out += n as u64;
+                        stats.inner_calls += 1;
+                    }
+                    if let Some(progress) = this.progress {
+                        progress(n);
+                    }
                 }
                 Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => {}
                 Poll::Ready(Err(e)) => {
@@ -121,6 +586,15 @@ impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
 
         if *this.written > 0 {
             this.buf.drain(..*this.written);
+            if let Some(checkpoint) = this.checkpoint {
+                *checkpoint = checkpoint.saturating_sub(*this.written);
+            }
+        }
+        this.staged.drain(..*this.staged_written);
+        *this.staged_written = 0;
+        if this.max_capacity.is_some() && this.buf.capacity() > *this.capacity {
+            // Give back the room an expanded chunk needed
+            this.buf.shrink_to(*this.capacity);
         }
         *this.written = 0;
         *this.transformed = false; // Reset transformed flag when buffer is drained
@@ -132,7 +606,7 @@ impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
     /// This function ensures that the internal buffer is transformed before writing.
     /// 
     /// returns the number of bytes written to the internal buffer.
-    fn partial_write(self: Pin<&mut Self>, buf: &[u8]) -> usize {
+    fn partial_write(self: Pin<&mut Self>, buf: &[u8]) -> io::Result<usize> {
         let this = self.project();
         debug_assert!(
             !*this.transformed,
@@ -144,35 +618,158 @@ impl<'a, W: AsyncWrite> AsyncMapWriter<'a, W> {
 
         // Only append if there's space.
         if to_read > 0 {
+            let old_len = this.buf.len();
             this.buf.extend_from_slice(&buf[..to_read]);
             // If not yet transformed, process the accumulated data.
             if !*this.transformed {
-                (this.process_fn).map_write(this.buf);
+                if *this.enabled {
+                    let len = this.buf.len();
+                    if let Err(e) = (this.process_fn).try_map_write_seq(this.buf, *this.invocations)
+                    {
+                        // The write failed, so the new data must not stay in the buffer
+                        this.buf.truncate(old_len);
+                        return Err(e);
+                    }
+                    check_growth(this.buf, *this.max_capacity)?;
+                    if let Some(stats) = this.stats {
+                        stats.record_chunk(len);
+                    }
+                    *this.invocations += 1;
+                }
                 *this.transformed = true;
+                *this.checkpoint = None;
             }
         }
-        to_read
+        Ok(to_read)
     }
 }
 
-impl<W: AsyncWrite> AsyncWrite for AsyncMapWriter<'_, W> {
-    fn poll_write(
+impl<W: AsyncWrite + Unpin, F: TryMapWriteFn> AsyncMapWriter<'_, W, F> {
+    /// Writes `n` copies of `fill`, as if they were written with `write_all`.
+    ///
+    /// The filler goes through the internal buffer and the mapping function like any other data.
+    pub async fn write_padding(&mut self, n: usize, fill: u8) -> io::Result<()> {
+        let filler = [fill; 256];
+        let mut remaining = n;
+        while remaining > 0 {
+            let amt = std::cmp::min(remaining, filler.len());
+            self.write_all(&filler[..amt]).await?;
+            remaining -= amt;
+        }
+        Ok(())
+    }
+
+    /// Writes all of `buf` and flushes it, so it reaches the underlying writer as a distinct message.
+    ///
+    /// Any data written before is flushed first, so the mapping function sees this message on its own,
+    /// as long as it fits within the buffer capacity.
+    pub async fn write_flush(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.flush().await?;
+        self.write_all(buf).await?;
+        self.flush().await
+    }
+
+    /// Ends the transaction started with [`AsyncMapWriter::begin`], and flushes its data.
+    pub async fn commit(&mut self) -> io::Result<()> {
+        self.checkpoint = None;
+        self.flush().await
+    }
+
+    /// Flushes all buffered data, then returns a reference to the underlying writer.
+    ///
+    /// Unlike accessing the underlying writer directly, this guarantees it has received everything
+    /// written so far, mapped.
+    pub async fn flush_and_get_ref(&mut self) -> io::Result<&W> {
+        self.flush().await?;
+        Ok(&self.inner)
+    }
+
+    /// Writes out all buffered data, mapped, then consumes the `AsyncMapWriter` and returns the underlying writer.
+    ///
+    /// Unlike [`AsyncMapWriter::into_inner`], no data is lost, and errors from the mapping function
+    /// or the underlying writer are returned. The underlying writer itself is not flushed.
+    pub async fn into_inner_flushed(mut self) -> io::Result<W> {
+        futures_lite::future::poll_fn(|cx| Pin::new(&mut self).poll_flush_buf(cx)).await?;
+        Ok(self.inner)
+    }
+}
+
+// The `AsyncWrite` implementation, wrapped to keep a copy of the errors returned
+impl<W: AsyncWrite, F: TryMapWriteFn> AsyncMapWriter<'_, W, F> {
+    fn poll_write_mapped(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize>> {
-        // Flush the internal buffer if adding new data would exceed capacity.
-        if self.buf.len() + buf.len() > self.buf.capacity() {
+        let room = self.min_transform_size.saturating_sub(self.buf.len());
+        if room > 0 && !self.transformed {
+            // Not enough data to map yet, just buffer it
+            let this = self.project();
+            let amt = std::cmp::min(buf.len(), room);
+            this.buf.extend_from_slice(&buf[..amt]);
+            if let Some(stats) = this.stats {
+                stats.bytes_in += amt as u64;
+            }
+            return Poll::Ready(Ok(amt));
+        }
+        if buf.len() >= self.buf.capacity() && !self.buf.is_empty() && !self.transformed {
+            // For large writes on top of buffered data, map both now,
+            // so they can be written out together with a single vectored write.
+            let this = self.as_mut().project();
+            let amt = std::cmp::min(buf.len(), *this.capacity);
+            if *this.enabled {
+                let len = this.buf.len();
+                (this.process_fn).try_map_write_seq(this.buf, *this.invocations)?;
+                check_growth(this.buf, *this.max_capacity)?;
+                *this.invocations += 1;
+                *this.transformed = true;
+                *this.checkpoint = None;
+                if let Some(stats) = this.stats {
+                    stats.record_chunk(len);
+                }
+                this.staged.extend_from_slice(&buf[..amt]);
+                if let Err(e) = (this.process_fn).try_map_write_seq(this.staged, *this.invocations)
+                {
+                    // Only the buffered data was mapped, the new data isn't written
+                    this.staged.clear();
+                    return Poll::Ready(Err(e));
+                }
+                *this.invocations += 1;
+                if let Some(stats) = this.stats {
+                    stats.record_chunk(amt);
+                }
+            } else {
+                this.staged.extend_from_slice(&buf[..amt]);
+            }
+            if let Some(stats) = this.stats {
+                stats.bytes_in += amt as u64;
+            }
+            *this.transformed = true;
+            *this.checkpoint = None;
+            return Poll::Ready(Ok(amt));
+        }
+
+        // Flush the internal buffer if adding new data would exceed capacity,
+        // or if it was already transformed, so new data doesn't skip the mapping function.
+        if self.transformed || self.buf.len() + buf.len() > self.buf.capacity() {
             ready!(self.as_mut().poll_flush_buf(cx))?;
         }
 
         if buf.len() < self.buf.capacity() {
             // For small writes, write into our internal buffer so that the
             // mapping function is applied later in poll_flush_buf.
-            return Pin::new(&mut *self.project().buf).poll_write(cx, buf);
+            let this = self.project();
+            this.buf.extend_from_slice(buf);
+            if let Some(stats) = this.stats {
+                stats.bytes_in += buf.len() as u64;
+            }
+            return Poll::Ready(Ok(buf.len()));
         }
         // If data is large, process it before writing using the internal buffer.
-        let read = self.as_mut().partial_write(buf);
+        let read = self.as_mut().partial_write(buf)?;
+        if let Some(stats) = self.project().stats {
+            stats.bytes_in += read as u64;
+        }
 
         // Instead of attempting to write immediately and potentially leaving
         // data behind, we'll just report however many bytes we've processed
@@ -180,14 +777,132 @@ impl<W: AsyncWrite> AsyncWrite for AsyncMapWriter<'_, W> {
         Poll::Ready(Ok(read))
     }
 
-    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+    fn poll_flush_mapped(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
         ready!(self.as_mut().poll_flush_buf(cx))?;
+        if !self.propagate_flush {
+            return Poll::Ready(Ok(()));
+        }
         self.get_pin_mut().poll_flush(cx)
     }
 
+    fn poll_close_mapped(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if !self.finished {
+            // Write out anything already transformed, so the final chunk only holds new data
+            if self.transformed {
+                ready!(self.as_mut().poll_flush_buf(cx))?;
+            }
+            let this = self.as_mut().project();
+            if *this.enabled {
+                let len = this.buf.len();
+                this.process_fn.try_map_write_final(this.buf)?;
+                check_growth(this.buf, *this.max_capacity)?;
+                if let Some(stats) = this.stats {
+                    stats.record_chunk(len);
+                }
+                *this.invocations += 1;
+            }
+            *this.transformed = true;
+            *this.checkpoint = None;
+            *this.finished = true;
+        }
+        if self.closed {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        ready!(self.as_mut().get_pin_mut().poll_close(cx))?;
+        *self.project().closed = true;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Keeps a copy of the error in `res`, see [`AsyncMapWriter::last_error`].
+    fn record_error<T>(self: Pin<&mut Self>, res: Result<T>) -> Result<T> {
+        if let Err(e) = &res {
+            *self.project().last_error = Some(copy_error(e));
+        }
+        res
+    }
+}
+
+impl<W: AsyncWrite, F: TryMapWriteFn> AsyncWrite for AsyncMapWriter<'_, W, F> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        let res = ready!(self.as_mut().poll_write_mapped(cx, buf));
+        Poll::Ready(self.record_error(res))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize>> {
+        let total: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if !self.transformed && self.buf.len() + total <= self.buf.capacity() {
+            // Buffer all the slices at once, so they're mapped and written out together
+            let this = self.project();
+            bufs.iter().for_each(|buf| this.buf.extend_from_slice(buf));
+            if let Some(stats) = this.stats {
+                stats.bytes_in += total as u64;
+            }
+            return Poll::Ready(Ok(total));
+        }
+        let buf = bufs
+            .iter()
+            .find(|buf| !buf.is_empty())
+            .map_or(&[][..], |buf| &**buf);
+        self.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let res = ready!(self.as_mut().poll_flush_mapped(cx));
+        Poll::Ready(self.record_error(res))
+    }
+
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let res = ready!(self.as_mut().poll_close_mapped(cx));
+        Poll::Ready(self.record_error(res))
+    }
+}
+
+impl<W: fmt::Debug, F> fmt::Debug for AsyncMapWriter<'_, W, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncMapWriter")
+            .field("inner", &self.inner)
+            .field("capacity", &self.capacity)
+            .field("written", &self.written)
+            .field("buffered", &self.buf.len())
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: AsyncWrite + AsyncSeek, F: TryMapWriteFn> AsyncSeek for AsyncMapWriter<'_, W, F> {
+    /// Maps and writes out all the buffered data, then seeks the underlying writer.
+    ///
+    /// The data written before and after the seek is never mapped as one chunk.
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<Result<u64>> {
         ready!(self.as_mut().poll_flush_buf(cx))?;
-        self.get_pin_mut().poll_close(cx)
+        self.get_pin_mut().poll_seek(cx, pos)
+    }
+}
+
+/// Fails if the mapping function grew `buf` past `max_capacity`, discarding its data.
+fn check_growth(buf: &mut Vec<u8>, max_capacity: Option<usize>) -> io::Result<()> {
+    match max_capacity {
+        Some(max) if buf.len() > max => {
+            buf.clear();
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "mapped chunk exceeds the maximum buffer capacity",
+            ))
+        }
+        _ => Ok(()),
     }
 }
 
@@ -197,7 +912,7 @@ pub trait AsyncMapWrite<'a, W> {
     /// 
     /// This function will apply the mapping function to the data before writing it to the underlying writer.
     /// This also buffers the data (with a buffer size of 8KB) to optimize writes.
-    fn map(self, process_fn: impl MapWriteFn + 'a) -> AsyncMapWriter<'a, W>
+    fn map<F: MapWriteFn + 'a>(self, process_fn: F) -> AsyncMapWriter<'a, W, F>
     where
         Self: Sized,
     {
@@ -209,19 +924,23 @@ pub trait AsyncMapWrite<'a, W> {
     /// This function allows you to specify the size of the internal buffer used for writing.
     /// The default buffer size is 8KB.
     /// If you need to optimize for larger writes, you can increase this size.
-    fn map_with_capacity(
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    fn map_with_capacity<F: MapWriteFn + 'a>(
         self,
-        process_fn: impl MapWriteFn + 'a,
+        process_fn: F,
         capacity: usize,
-    ) -> AsyncMapWriter<'a, W>;
+    ) -> AsyncMapWriter<'a, W, F>;
 }
 
 impl<'a, W: AsyncWrite> AsyncMapWrite<'a, W> for W {
-    fn map_with_capacity(
+    fn map_with_capacity<F: MapWriteFn + 'a>(
         self,
-        process_fn: impl MapWriteFn + 'a,
+        process_fn: F,
         capacity: usize,
-    ) -> AsyncMapWriter<'a, W> {
-        AsyncMapWriter::with_capacity(self, process_fn, capacity)
+    ) -> AsyncMapWriter<'a, W, F> {
+        AsyncMapWriter::from_fn_with_capacity(self, process_fn, capacity)
     }
 }